@@ -0,0 +1,26 @@
+//! Parses the effect text of every card in a database and lists the cards the effect parser
+//! cannot handle yet.
+//!
+//! Usage: `cargo run -p data --example effect_coverage [path/to/card_db.jsonl]`
+
+use data::{effect, CardDb};
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "cache/en/card_db.jsonl".to_string());
+
+    let cards = CardDb::load(&path).unwrap();
+
+    let unsupported = effect::unsupported_cards(&cards);
+    for (id, error) in unsupported.iter() {
+        println!("{id}: {error}");
+    }
+
+    println!(
+        "Parsed {}/{} cards ({} unsupported)",
+        cards.len() - unsupported.len(),
+        cards.len(),
+        unsupported.len()
+    );
+}
@@ -0,0 +1,372 @@
+//! Manual corrections to scraped card data.
+//!
+//! Corrections are kept in their own JSONL file (`cache/<region>/corrections.jsonl`), one line per
+//! card holding only the fields that differ from the scraped data, so the scraper can overwrite
+//! `card_db.jsonl` freely and the fixes are applied again when the database is loaded.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+use crate::{Attribute, CardData, CardDb, CardId, CardType, Color, Rarity, Subtype};
+
+#[derive(Error, Debug)]
+pub enum CorrectionError {
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+
+    #[error("Failed to write {0}: {1}")]
+    Write(PathBuf, #[source] std::io::Error),
+
+    #[error("Invalid correction on line {line}: {source}")]
+    Parse {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// Distinguishes a field set to `null` (`Some(None)`) from a field that is missing (`None`)
+fn present<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// The fields of a card that were changed. `None` keeps the scraped value.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CardPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ty: Option<CardType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rarity: Option<Rarity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_life: Option<usize>,
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub power: Option<Option<usize>>,
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub counter: Option<Option<usize>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Vec<Color>>,
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub effect: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub trigger: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtype: Option<Vec<Subtype>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribute: Option<Vec<Attribute>>,
+}
+
+macro_rules! patch_fields {
+    ($mac:ident) => {
+        $mac!(
+            ty, rarity, name, cost_life, power, counter, color, effect, trigger, subtype, attribute
+        )
+    };
+}
+
+impl CardPatch {
+    /// The fields of `edited` that differ from `original`
+    pub fn diff(original: &CardData, edited: &CardData) -> Self {
+        let mut patch = Self::default();
+        macro_rules! diff {
+            ($($field:ident),*) => {
+                $(
+                    if original.$field != edited.$field {
+                        patch.$field = Some(edited.$field.clone());
+                    }
+                )*
+            };
+        }
+        patch_fields!(diff);
+        patch
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply(&self, card: &mut CardData) {
+        macro_rules! apply {
+            ($($field:ident),*) => {
+                $(
+                    if let Some(value) = &self.$field {
+                        card.$field = value.clone();
+                    }
+                )*
+            };
+        }
+        patch_fields!(apply);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Correction {
+    pub id: CardId,
+    pub reviewer: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub changes: CardPatch,
+}
+
+impl Correction {
+    pub fn new(id: CardId, reviewer: impl Into<String>, changes: CardPatch) -> Self {
+        Self {
+            id,
+            reviewer: reviewer.into(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            changes,
+        }
+    }
+}
+
+/// Every correction, at most one per card
+#[derive(Debug, Clone, Default)]
+pub struct Corrections {
+    corrections: BTreeMap<CardId, Correction>,
+}
+
+impl Corrections {
+    /// Loads a corrections file. A missing file means nothing was corrected yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CorrectionError> {
+        let path = path.as_ref();
+        let jsonl = match std::fs::read_to_string(path) {
+            Ok(jsonl) => jsonl,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(CorrectionError::Io(path.to_path_buf(), e)),
+        };
+
+        let mut corrections = Self::default();
+        for (index, line) in jsonl.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let correction: Correction =
+                serde_json::from_str(line).map_err(|source| CorrectionError::Parse {
+                    line: index + 1,
+                    source,
+                })?;
+            corrections.corrections.insert(correction.id, correction);
+        }
+
+        Ok(corrections)
+    }
+
+    /// Writes the corrections sorted by card id, replacing the file atomically
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CorrectionError> {
+        let mut jsonl = String::new();
+        for correction in self.corrections.values() {
+            jsonl.push_str(&serde_json::to_string(correction).unwrap());
+            jsonl.push('\n');
+        }
+
+        let path = path.as_ref();
+        let temp = path.with_extension("jsonl.tmp");
+        std::fs::write(&temp, jsonl).map_err(|e| CorrectionError::Write(temp.clone(), e))?;
+        std::fs::rename(&temp, path).map_err(|e| CorrectionError::Write(path.to_path_buf(), e))
+    }
+
+    pub fn get(&self, id: CardId) -> Option<&Correction> {
+        self.corrections.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Correction> {
+        self.corrections.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.corrections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.corrections.is_empty()
+    }
+
+    /// Records a correction, replacing any previous one for the card. A correction without changes
+    /// removes the card's entry, as the card matches the scraped data again.
+    pub fn set(&mut self, correction: Correction) {
+        if correction.changes.is_empty() {
+            self.corrections.remove(&correction.id);
+        } else {
+            self.corrections.insert(correction.id, correction);
+        }
+    }
+
+    /// Applies every correction to the database. Corrections for cards that are not in the
+    /// database are returned, as the card id was probably changed by the scraper.
+    pub fn apply(&self, db: &mut CardDb) -> Vec<CardId> {
+        self.corrections
+            .values()
+            .filter_map(|correction| {
+                db.update(correction.id, |card| correction.changes.apply(card))
+                    .is_none()
+                    .then_some(correction.id)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn card(id: &str) -> CardData {
+        serde_json::from_str(&format!(
+            r#"{{"id":"{id}","release_set":"OP01","rarity":"C","ty":"CHARACTER","name":"Nami","image_name":"{id}.png","cost_life":1,"power":2000,"counter":1000,"color":["Red"],"effect":"[On Play] Draw 1 card.","trigger":"[Trigger] Play this card.","subtype":[],"attribute":["Special"]}}"#
+        ))
+        .unwrap()
+    }
+
+    fn id(id: &str) -> CardId {
+        CardId::from_str(id).unwrap()
+    }
+
+    fn patch(json: &str) -> CardPatch {
+        let patch: CardPatch = serde_json::from_str(json).unwrap();
+        let round_trip = serde_json::to_string(&patch).unwrap();
+        assert_eq!(
+            serde_json::from_str::<CardPatch>(&round_trip).unwrap(),
+            patch
+        );
+        patch
+    }
+
+    #[test]
+    fn missing_fields_keep_the_scraped_value() {
+        let empty = patch("{}");
+        assert!(empty.is_empty());
+        assert_eq!(serde_json::to_string(&empty).unwrap(), "{}");
+
+        let mut patched = card("OP01-016");
+        empty.apply(&mut patched);
+        assert_eq!(patched, card("OP01-016"));
+    }
+
+    #[test]
+    fn null_fields_clear_the_scraped_value() {
+        let clear = patch(r#"{"power":null,"counter":null,"effect":null,"trigger":null}"#);
+        assert_eq!(clear.power, Some(None));
+        assert_eq!(clear.effect, Some(None));
+        assert_eq!(clear.name, None);
+        assert!(!clear.is_empty());
+        assert_eq!(
+            serde_json::to_string(&clear).unwrap(),
+            r#"{"power":null,"counter":null,"effect":null,"trigger":null}"#
+        );
+
+        let mut card = card("OP01-016");
+        clear.apply(&mut card);
+        assert_eq!((card.power, card.counter), (None, None));
+        assert_eq!((card.effect, card.trigger), (None, None));
+        assert_eq!(card.name, "Nami");
+    }
+
+    #[test]
+    fn set_fields_replace_the_scraped_value() {
+        let set = patch(r#"{"power":3000,"trigger":"[Trigger] Draw 1 card.","color":["Blue"]}"#);
+        assert_eq!(set.power, Some(Some(3000)));
+
+        let mut card = card("OP01-016");
+        set.apply(&mut card);
+        assert_eq!(card.power, Some(3000));
+        assert_eq!(card.trigger.as_deref(), Some("[Trigger] Draw 1 card."));
+        assert_eq!(card.color, [Color::Blue]);
+        assert_eq!(card.counter, Some(1000));
+
+        assert!(serde_json::from_str::<CardPatch>(r#"{"image_name":"x.png"}"#).is_err());
+    }
+
+    #[test]
+    fn diff_records_cleared_fields() {
+        let original = card("OP01-016");
+        let mut edited = original.clone();
+        assert!(CardPatch::diff(&original, &edited).is_empty());
+
+        edited.effect = None;
+        edited.cost_life = 2;
+        let diff = CardPatch::diff(&original, &edited);
+        assert_eq!(
+            diff,
+            CardPatch {
+                cost_life: Some(2),
+                effect: Some(None),
+                ..Default::default()
+            }
+        );
+
+        let mut patched = original.clone();
+        diff.apply(&mut patched);
+        assert_eq!(patched, edited);
+    }
+
+    #[test]
+    fn corrections_for_unknown_cards_are_returned() {
+        let mut db = CardDb::new([card("OP01-016")]);
+        let mut corrections = Corrections::default();
+        corrections.set(Correction::new(
+            id("OP01-016"),
+            "reviewer",
+            patch(r#"{"power":null}"#),
+        ));
+        corrections.set(Correction::new(
+            id("OP01-099"),
+            "reviewer",
+            patch(r#"{"name":"Robin"}"#),
+        ));
+        corrections.set(Correction::new(id("OP01-017"), "reviewer", patch("{}")));
+        assert_eq!(corrections.len(), 2);
+
+        assert_eq!(corrections.apply(&mut db), [id("OP01-099")]);
+        assert_eq!(db.get(id("OP01-016")).unwrap().power, None);
+        assert!(!db.contains(id("OP01-099")));
+    }
+
+    #[test]
+    fn corrections_are_saved_and_loaded() {
+        let dir = std::env::temp_dir().join(format!("data-corrections-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("corrections.jsonl");
+        assert!(Corrections::load(&path).unwrap().is_empty());
+
+        let mut corrections = Corrections::default();
+        let correction = Correction::new(id("OP01-016"), "reviewer", patch(r#"{"trigger":null}"#));
+        corrections.set(correction.clone());
+        corrections.save(&path).unwrap();
+
+        let loaded = Corrections::load(&path).unwrap();
+        assert_eq!(loaded.get(id("OP01-016")), Some(&correction));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
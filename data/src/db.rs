@@ -0,0 +1,355 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::RangeBounds,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{group_printings, query::Query, CardData, CardId, CardType, Color, SetId, Subtype};
+
+#[derive(Error, Debug)]
+pub enum CardDbError {
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+
+    #[error("Invalid card on line {line}: {source}")]
+    Parse {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// All known cards, one per [`CardId`] with their printings grouped, kept in id order. The
+/// secondary indexes store positions into that order so lookups through them are also sorted.
+#[derive(Debug, Clone, Default)]
+pub struct CardDb {
+    cards: Vec<CardData>,
+    by_id: HashMap<CardId, usize>,
+    by_set: HashMap<SetId, Vec<usize>>,
+    by_color: HashMap<Color, Vec<usize>>,
+    by_type: HashMap<CardType, Vec<usize>>,
+    by_subtype: HashMap<Subtype, Vec<usize>>,
+    by_cost: BTreeMap<usize, Vec<usize>>,
+    /// Keyed by lowercase name
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+impl CardDb {
+    pub fn new(cards: impl IntoIterator<Item = CardData>) -> Self {
+        let mut db = Self {
+            cards: group_printings(cards),
+            ..Default::default()
+        };
+        db.reindex();
+        db
+    }
+
+    /// Loads a `card_db.jsonl` file as written by the scraper
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CardDbError> {
+        let path = path.as_ref();
+        let jsonl =
+            std::fs::read_to_string(path).map_err(|e| CardDbError::Io(path.to_path_buf(), e))?;
+        Self::from_jsonl(&jsonl)
+    }
+
+    pub fn from_jsonl(jsonl: &str) -> Result<Self, CardDbError> {
+        let cards = jsonl
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str::<CardData>(line).map_err(|source| CardDbError::Parse {
+                    line: index + 1,
+                    source,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::new(cards))
+    }
+
+    /// Serializes the database back into the JSONL format, one card per line
+    pub fn to_jsonl(&self) -> String {
+        let mut jsonl = String::new();
+        for card in self.cards.iter() {
+            jsonl.push_str(&serde_json::to_string(card).unwrap());
+            jsonl.push('\n');
+        }
+        jsonl
+    }
+
+    fn reindex(&mut self) {
+        self.by_id.clear();
+        self.by_set.clear();
+        self.by_color.clear();
+        self.by_type.clear();
+        self.by_subtype.clear();
+        self.by_cost.clear();
+        self.by_name.clear();
+
+        for (index, card) in self.cards.iter().enumerate() {
+            self.by_id.insert(card.id, index);
+            self.by_set.entry(card.id.set).or_default().push(index);
+            for color in card.color.iter() {
+                self.by_color.entry(*color).or_default().push(index);
+            }
+            self.by_type.entry(card.ty).or_default().push(index);
+            for subtype in card.subtype.iter() {
+                self.by_subtype.entry(*subtype).or_default().push(index);
+            }
+            self.by_cost.entry(card.cost_life).or_default().push(index);
+            self.by_name
+                .entry(card.name.to_lowercase())
+                .or_default()
+                .push(index);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    pub fn get(&self, id: CardId) -> Option<&CardData> {
+        self.by_id.get(&id).map(|index| &self.cards[*index])
+    }
+
+    pub fn contains(&self, id: CardId) -> bool {
+        self.by_id.contains_key(&id)
+    }
+
+    /// Position of the card in id order
+    pub fn position(&self, id: CardId) -> Option<usize> {
+        self.by_id.get(&id).copied()
+    }
+
+    /// Card at the given position in id order
+    pub fn get_index(&self, index: usize) -> Option<&CardData> {
+        self.cards.get(index)
+    }
+
+    /// All cards in id order
+    pub fn iter(&self) -> std::slice::Iter<'_, CardData> {
+        self.cards.iter()
+    }
+
+    /// Cards whose id belongs to the set, which excludes reprints of other sets' cards
+    pub fn by_set(&self, set: SetId) -> impl Iterator<Item = &CardData> {
+        self.indexed(self.by_set.get(&set))
+    }
+
+    pub fn by_color(&self, color: Color) -> impl Iterator<Item = &CardData> {
+        self.indexed(self.by_color.get(&color))
+    }
+
+    pub fn by_type(&self, ty: CardType) -> impl Iterator<Item = &CardData> {
+        self.indexed(self.by_type.get(&ty))
+    }
+
+    pub fn by_subtype(&self, subtype: Subtype) -> impl Iterator<Item = &CardData> {
+        self.indexed(self.by_subtype.get(&subtype))
+    }
+
+    /// Cards whose cost (or life, for Leaders) is within the range, ordered by cost and then id
+    pub fn by_cost(&self, cost: impl RangeBounds<usize>) -> impl Iterator<Item = &CardData> {
+        self.by_cost
+            .range(cost)
+            .flat_map(|(_, indices)| indices.iter().map(|index| &self.cards[*index]))
+    }
+
+    /// Cards with exactly this name, ignoring case
+    pub fn by_name(&self, name: &str) -> impl Iterator<Item = &CardData> {
+        self.indexed(self.by_name.get(&name.to_lowercase()))
+    }
+
+    /// Cards matching a search query, in id order
+    pub fn search<'a>(&'a self, query: &'a Query) -> impl Iterator<Item = &'a CardData> {
+        query.filter(self.cards.iter())
+    }
+
+    /// The ids of every set that has cards, in order
+    pub fn sets(&self) -> Vec<SetId> {
+        let mut sets: Vec<SetId> = self.by_set.keys().copied().collect();
+        sets.sort();
+        sets
+    }
+
+    /// Modifies a card in place, updating the indexes afterwards. The card's id must not be
+    /// changed. Returns `None` if no card has the id.
+    pub fn update<R>(&mut self, id: CardId, f: impl FnOnce(&mut CardData) -> R) -> Option<R> {
+        let index = *self.by_id.get(&id)?;
+        let result = f(&mut self.cards[index]);
+        assert_eq!(
+            self.cards[index].id, id,
+            "CardDb::update must not change the card id"
+        );
+        self.reindex();
+        Some(result)
+    }
+
+    /// Adds a card, replacing the card with the same id if there is one
+    pub fn insert(&mut self, card: CardData) -> Option<CardData> {
+        let previous = match self.cards.binary_search_by_key(&card.id, |card| card.id) {
+            Ok(index) => Some(std::mem::replace(&mut self.cards[index], card)),
+            Err(index) => {
+                self.cards.insert(index, card);
+                None
+            }
+        };
+        self.reindex();
+        previous
+    }
+
+    fn indexed<'a>(
+        &'a self,
+        indices: Option<&'a Vec<usize>>,
+    ) -> impl Iterator<Item = &'a CardData> {
+        indices
+            .into_iter()
+            .flatten()
+            .map(|index| &self.cards[*index])
+    }
+}
+
+impl<'a> IntoIterator for &'a CardDb {
+    type Item = &'a CardData;
+    type IntoIter = std::slice::Iter<'a, CardData>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cards.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn card(id: &str, name: &str, cost: usize, color: &str) -> CardData {
+        serde_json::from_str(&format!(
+            r#"{{"id":"{id}","release_set":"OP01","rarity":"C","ty":"CHARACTER","name":"{name}","image_name":"{id}.png","cost_life":{cost},"power":1000,"counter":null,"color":["{color}"],"effect":null,"trigger":null,"subtype":[],"attribute":[]}}"#
+        ))
+        .unwrap()
+    }
+
+    fn id(id: &str) -> CardId {
+        CardId::from_str(id).unwrap()
+    }
+
+    fn ids<'a>(cards: impl Iterator<Item = &'a CardData>) -> Vec<String> {
+        cards.map(|card| card.id.to_string()).collect()
+    }
+
+    /// Cards given out of id order, with two cards named Nami in different sets
+    fn db() -> CardDb {
+        CardDb::new([
+            card("OP01-020", "Nami", 1, "Red"),
+            card("OP01-003", "Zoro", 3, "Green"),
+            card("ST01-007", "Nami", 1, "Red"),
+            card("OP01-010", "Usopp", 2, "Red"),
+        ])
+    }
+
+    #[test]
+    fn lookups_are_in_id_order() {
+        let db = db();
+        assert_eq!(
+            ids(db.iter()),
+            ["ST01-007", "OP01-003", "OP01-010", "OP01-020"]
+        );
+        assert_eq!(db.get(id("OP01-003")).unwrap().name, "Zoro");
+        assert!(db.get(id("OP01-004")).is_none());
+        assert_eq!(db.position(id("OP01-010")), Some(2));
+
+        assert_eq!(
+            ids(db.by_set(SetId::Booster(1))),
+            ["OP01-003", "OP01-010", "OP01-020"]
+        );
+        assert_eq!(
+            ids(db.by_color(Color::Red)),
+            ["ST01-007", "OP01-010", "OP01-020"]
+        );
+        assert_eq!(db.sets(), [SetId::Starter(1), SetId::Booster(1)]);
+    }
+
+    #[test]
+    fn cost_ranges_are_ordered_by_cost_then_id() {
+        let db = db();
+        assert_eq!(ids(db.by_cost(1..=2)), ["ST01-007", "OP01-020", "OP01-010"]);
+        assert_eq!(ids(db.by_cost(3..)), ["OP01-003"]);
+        assert_eq!(ids(db.by_cost(..1)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn names_ignore_case() {
+        let db = db();
+        assert_eq!(ids(db.by_name("nami")), ["ST01-007", "OP01-020"]);
+        assert_eq!(ids(db.by_name("NAMI")), ["ST01-007", "OP01-020"]);
+        assert_eq!(ids(db.by_name("Nam")), Vec::<String>::new());
+    }
+
+    #[test]
+    fn update_keeps_the_indexes_in_sync() {
+        let mut db = db();
+        let result = db.update(id("OP01-010"), |card| {
+            card.color = vec![Color::Blue];
+            card.cost_life = 5;
+            card.name = "Sogeking".to_string();
+        });
+        assert_eq!(result, Some(()));
+        assert!(db.update(id("OP01-004"), |_| ()).is_none());
+
+        assert_eq!(ids(db.by_color(Color::Red)), ["ST01-007", "OP01-020"]);
+        assert_eq!(ids(db.by_color(Color::Blue)), ["OP01-010"]);
+        assert_eq!(ids(db.by_cost(2..=2)), Vec::<String>::new());
+        assert_eq!(ids(db.by_cost(5..)), ["OP01-010"]);
+        assert_eq!(ids(db.by_name("usopp")), Vec::<String>::new());
+        assert_eq!(ids(db.by_name("sogeking")), ["OP01-010"]);
+    }
+
+    #[test]
+    fn insert_keeps_the_indexes_in_sync() {
+        let mut db = db();
+        assert!(db.insert(card("OP01-005", "Nami", 4, "Red")).is_none());
+        assert_eq!(db.len(), 5);
+        assert_eq!(
+            ids(db.by_name("nami")),
+            ["ST01-007", "OP01-005", "OP01-020"]
+        );
+        assert_eq!(db.position(id("OP01-020")), Some(4));
+        assert_eq!(ids(db.by_cost(4..=4)), ["OP01-005"]);
+
+        let previous = db.insert(card("OP01-020", "Nami", 2, "Purple")).unwrap();
+        assert_eq!(previous.color, [Color::Red]);
+        assert_eq!(db.len(), 5);
+        assert_eq!(
+            ids(db.by_color(Color::Red)),
+            ["ST01-007", "OP01-005", "OP01-010"]
+        );
+        assert_eq!(ids(db.by_color(Color::Purple)), ["OP01-020"]);
+        assert_eq!(ids(db.by_cost(2..=2)), ["OP01-010", "OP01-020"]);
+    }
+
+    #[test]
+    fn jsonl_skips_blank_lines_and_reports_invalid_ones() {
+        let db = CardDb::from_jsonl(&format!(
+            "{}\n\n{}\n",
+            serde_json::to_string(&card("OP01-003", "Zoro", 3, "Green")).unwrap(),
+            serde_json::to_string(&card("ST01-007", "Nami", 1, "Red")).unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(ids(db.iter()), ["ST01-007", "OP01-003"]);
+        assert_eq!(CardDb::from_jsonl(&db.to_jsonl()).unwrap().len(), 2);
+
+        match CardDb::from_jsonl(&format!("{}\n{{}}\n", db.to_jsonl().trim_end())) {
+            Err(CardDbError::Parse { line, .. }) => assert_eq!(line, 3),
+            other => panic!("expected an invalid line, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,501 @@
+use std::{collections::BTreeMap, fmt::Write, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{CardData, CardId, CardIdParseError, CardType, Color};
+
+/// Number of cards in a main deck, not counting the Leader
+pub const DECK_SIZE: usize = 50;
+
+/// Maximum number of copies of a single card number in a main deck
+pub const MAX_COPIES: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct Deck {
+    pub leader: Option<CardId>,
+    pub cards: Vec<CardId>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DeckError {
+    #[error("The deck has no Leader")]
+    MissingLeader,
+
+    #[error("Unknown card '{0}'")]
+    UnknownCard(CardId),
+
+    #[error("{0} is not a Leader card")]
+    NotALeader(CardId),
+
+    #[error("{0} is a Leader card and cannot be in the main deck")]
+    LeaderInDeck(CardId),
+
+    #[error("The deck must contain exactly {DECK_SIZE} cards ({0} found)")]
+    WrongSize(usize),
+
+    #[error("{card} appears {count} times (at most {MAX_COPIES} copies are allowed)")]
+    TooManyCopies { card: CardId, count: usize },
+
+    #[error("{card} shares no color with the Leader ({colors:?})")]
+    ColorMismatch { card: CardId, colors: Vec<Color> },
+}
+
+/// Errors found while importing a deck list or deck code. For deck codes, `line` is the position of
+/// the entry in the code, starting at 1.
+#[derive(Error, Debug)]
+pub enum DeckListError {
+    #[error("The deck list is empty")]
+    Empty,
+
+    #[error("Line {line}: expected '<count>x<card id>', found '{text}'")]
+    MalformedLine { line: usize, text: String },
+
+    #[error("Line {line}: invalid card count '{text}'")]
+    InvalidCount { line: usize, text: String },
+
+    #[error("Line {line}: {source}")]
+    InvalidCardId {
+        line: usize,
+        #[source]
+        source: CardIdParseError,
+    },
+
+    #[error("Line {line}: unknown card {card}")]
+    UnknownCard { line: usize, card: CardId },
+
+    #[error("Line {line}: {card} is a second Leader, a deck has exactly one")]
+    MultipleLeaders { line: usize, card: CardId },
+}
+
+struct DeckListEntry {
+    line: usize,
+    count: usize,
+    card: CardId,
+}
+
+impl Deck {
+    pub fn new(leader: CardId, cards: Vec<CardId>) -> Self {
+        Self {
+            leader: Some(leader),
+            cards,
+        }
+    }
+
+    /// Number of copies of each card in the main deck. Parallel art parses to the [`CardId`] of its
+    /// base card, so both are counted together.
+    pub fn copies(&self) -> BTreeMap<CardId, usize> {
+        let mut copies = BTreeMap::new();
+        for card in self.cards.iter() {
+            *copies.entry(*card).or_default() += 1;
+        }
+        copies
+    }
+
+    /// Checks the deck against the official construction rules, returning every violation found.
+    /// An empty list means the deck is legal.
+    pub fn validate<'a, F>(&self, lookup: F) -> Vec<DeckError>
+    where
+        F: Fn(CardId) -> Option<&'a CardData>,
+    {
+        let mut errors = vec![];
+
+        let leader = match self.leader {
+            Some(id) => match lookup(id) {
+                Some(card) if card.ty == CardType::Leader => Some(card),
+                Some(_) => {
+                    errors.push(DeckError::NotALeader(id));
+                    None
+                }
+                None => {
+                    errors.push(DeckError::UnknownCard(id));
+                    None
+                }
+            },
+            None => {
+                errors.push(DeckError::MissingLeader);
+                None
+            }
+        };
+
+        if self.cards.len() != DECK_SIZE {
+            errors.push(DeckError::WrongSize(self.cards.len()));
+        }
+
+        for (id, count) in self.copies() {
+            let Some(card) = lookup(id) else {
+                errors.push(DeckError::UnknownCard(id));
+                continue;
+            };
+
+            if card.ty == CardType::Leader {
+                errors.push(DeckError::LeaderInDeck(id));
+            }
+
+            if count > MAX_COPIES {
+                errors.push(DeckError::TooManyCopies { card: id, count });
+            }
+
+            if let Some(leader) = leader {
+                if !card.color.iter().any(|color| leader.color.contains(color)) {
+                    errors.push(DeckError::ColorMismatch {
+                        card: id,
+                        colors: card.color.clone(),
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Imports a deck list with one `<count>x<card id>` entry per line, e.g. `4xOP01-016`, as
+    /// exported by OPTCGSim and most deckbuilder sites. Blank lines and lines starting with `#`
+    /// are ignored. The Leader is recognized by its card type, so it can appear on any line.
+    pub fn from_deck_list<'a, F>(text: &str, lookup: F) -> Result<Self, Vec<DeckListError>>
+    where
+        F: Fn(CardId) -> Option<&'a CardData>,
+    {
+        let mut errors = vec![];
+        let mut entries = vec![];
+
+        for (index, text) in text.lines().enumerate() {
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+
+            match parse_list_entry(index + 1, text) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        Self::from_entries(entries, errors, lookup)
+    }
+
+    /// Exports the deck in the `<count>x<card id>` format, Leader first
+    pub fn to_deck_list(&self) -> String {
+        let mut list = String::new();
+        if let Some(leader) = self.leader {
+            writeln!(list, "1x{leader}").unwrap();
+        }
+
+        for (card, count) in self.copies() {
+            writeln!(list, "{count}x{card}").unwrap();
+        }
+
+        list
+    }
+
+    /// Imports a deck code as produced by [`Deck::to_code`]
+    pub fn from_code<'a, F>(code: &str, lookup: F) -> Result<Self, Vec<DeckListError>>
+    where
+        F: Fn(CardId) -> Option<&'a CardData>,
+    {
+        let mut errors = vec![];
+        let mut entries = vec![];
+
+        for (index, text) in code.trim().split('.').enumerate() {
+            if text.is_empty() {
+                continue;
+            }
+
+            match parse_code_entry(index + 1, text) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        Self::from_entries(entries, errors, lookup)
+    }
+
+    /// A single line code for sharing the deck, made of `<count><card id>` entries separated by
+    /// dots, e.g. `1OP01-001.4OP01-016.4OP01-025`
+    pub fn to_code(&self) -> String {
+        self.leader
+            .map(|leader| (leader, 1))
+            .into_iter()
+            .chain(self.copies())
+            .map(|(card, count)| format!("{count}{card}"))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    fn from_entries<'a, F>(
+        entries: Vec<DeckListEntry>,
+        mut errors: Vec<DeckListError>,
+        lookup: F,
+    ) -> Result<Self, Vec<DeckListError>>
+    where
+        F: Fn(CardId) -> Option<&'a CardData>,
+    {
+        if entries.is_empty() && errors.is_empty() {
+            return Err(vec![DeckListError::Empty]);
+        }
+
+        let mut deck = Self::default();
+        for DeckListEntry { line, count, card } in entries {
+            match lookup(card) {
+                None => errors.push(DeckListError::UnknownCard { line, card }),
+                Some(data) if data.ty == CardType::Leader => {
+                    if deck.leader.is_some() || count > 1 {
+                        errors.push(DeckListError::MultipleLeaders { line, card });
+                    } else {
+                        deck.leader = Some(card);
+                    }
+                }
+                Some(_) => deck.cards.extend((0..count).map(|_| card)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(deck)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn parse_list_entry(line: usize, text: &str) -> Result<DeckListEntry, DeckListError> {
+    let (count, card) = text
+        .split_once('x')
+        .ok_or_else(|| DeckListError::MalformedLine {
+            line,
+            text: text.to_string(),
+        })?;
+
+    parse_entry(line, count.trim(), card.trim())
+}
+
+fn parse_code_entry(line: usize, text: &str) -> Result<DeckListEntry, DeckListError> {
+    let split =
+        text.find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| DeckListError::MalformedLine {
+                line,
+                text: text.to_string(),
+            })?;
+    let (count, card) = text.split_at(split);
+
+    parse_entry(line, count, card)
+}
+
+fn parse_entry(line: usize, count: &str, card: &str) -> Result<DeckListEntry, DeckListError> {
+    let count = count
+        .parse::<usize>()
+        .ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(|| DeckListError::InvalidCount {
+            line,
+            text: count.to_string(),
+        })?;
+    let card =
+        CardId::from_str(card).map_err(|source| DeckListError::InvalidCardId { line, source })?;
+
+    Ok(DeckListEntry { line, count, card })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(id: &str, ty: &str, colors: &str) -> CardData {
+        serde_json::from_str(&format!(
+            r#"{{"id":"{id}","release_set":"OP01","rarity":"C","ty":"{ty}","name":"{id}","image_name":"{id}.png","cost_life":1,"power":1000,"counter":null,"color":[{colors}],"effect":null,"trigger":null,"subtype":[],"attribute":[]}}"#
+        ))
+        .unwrap()
+    }
+
+    fn cards() -> Vec<CardData> {
+        let mut cards = vec![
+            card("OP01-001", "LEADER", r#""Red""#),
+            card("OP01-002", "LEADER", r#""Red","Green""#),
+            card("OP01-040", "CHARACTER", r#""Blue""#),
+        ];
+        cards.extend((10..30).map(|n| card(&format!("OP01-{n:03}"), "CHARACTER", r#""Red""#)));
+        cards
+    }
+
+    fn id(id: &str) -> CardId {
+        CardId::from_str(id).unwrap()
+    }
+
+    /// A legal deck of 4 copies of 12 red Characters and 2 copies of a 13th one
+    fn legal_deck() -> Deck {
+        let cards = (10..23)
+            .flat_map(|n| std::iter::repeat_n(id(&format!("OP01-{n:03}")), MAX_COPIES))
+            .take(DECK_SIZE)
+            .collect();
+        Deck::new(id("OP01-001"), cards)
+    }
+
+    fn validate(deck: &Deck) -> Vec<DeckError> {
+        let cards = cards();
+        deck.validate(lookup(&cards))
+    }
+
+    #[test]
+    fn accepts_legal_deck() {
+        assert_eq!(validate(&legal_deck()), vec![]);
+    }
+
+    #[test]
+    fn rejects_wrong_size() {
+        let mut deck = legal_deck();
+        deck.cards.pop();
+        assert_eq!(validate(&deck), vec![DeckError::WrongSize(DECK_SIZE - 1)]);
+
+        deck.cards.extend([id("OP01-023"), id("OP01-023")]);
+        assert_eq!(validate(&deck), vec![DeckError::WrongSize(DECK_SIZE + 1)]);
+    }
+
+    #[test]
+    fn rejects_too_many_copies() {
+        let mut deck = legal_deck();
+        deck.cards[DECK_SIZE - 1] = id("OP01-010");
+        assert_eq!(
+            validate(&deck),
+            vec![DeckError::TooManyCopies {
+                card: id("OP01-010"),
+                count: MAX_COPIES + 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_leader_in_deck() {
+        let mut deck = legal_deck();
+        deck.cards[DECK_SIZE - 1] = id("OP01-002");
+        assert_eq!(
+            validate(&deck),
+            vec![DeckError::LeaderInDeck(id("OP01-002"))]
+        );
+
+        deck.leader = Some(id("OP01-010"));
+        assert_eq!(
+            validate(&deck),
+            vec![
+                DeckError::NotALeader(id("OP01-010")),
+                DeckError::LeaderInDeck(id("OP01-002")),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_color_mismatch() {
+        let mut deck = legal_deck();
+        deck.cards[DECK_SIZE - 1] = id("OP01-040");
+        assert_eq!(
+            validate(&deck),
+            vec![DeckError::ColorMismatch {
+                card: id("OP01-040"),
+                colors: vec![Color::Blue],
+            }]
+        );
+
+        // Sharing any color with the Leader is enough
+        deck.leader = Some(id("OP01-002"));
+        deck.cards[DECK_SIZE - 1] = id("OP01-022");
+        assert_eq!(validate(&deck), vec![]);
+    }
+
+    #[test]
+    fn rejects_unknown_cards() {
+        let mut deck = legal_deck();
+        deck.cards[DECK_SIZE - 1] = id("OP01-099");
+        deck.leader = Some(id("OP01-098"));
+        assert_eq!(
+            validate(&deck),
+            vec![
+                DeckError::UnknownCard(id("OP01-098")),
+                DeckError::UnknownCard(id("OP01-099")),
+            ]
+        );
+
+        deck.leader = None;
+        assert_eq!(validate(&deck)[0], DeckError::MissingLeader);
+    }
+
+    fn lookup<'a>(cards: &'a [CardData]) -> impl Fn(CardId) -> Option<&'a CardData> {
+        move |id| cards.iter().find(|card| card.id == id)
+    }
+
+    #[test]
+    fn deck_list_round_trip() {
+        let cards = cards();
+        let deck = legal_deck();
+        let list = deck.to_deck_list();
+        assert!(list.starts_with("1xOP01-001\n4xOP01-010\n4xOP01-011\n"));
+        assert!(list.ends_with("4xOP01-021\n2xOP01-022\n"));
+
+        let imported = Deck::from_deck_list(&list, lookup(&cards)).unwrap();
+        assert_eq!(imported, deck);
+
+        // Comments, blank lines and the position of the Leader don't matter
+        let list = format!(
+            "# Red aggro\n\n{}\n 1x OP01-001 ",
+            list.replacen("1xOP01-001\n", "", 1)
+        );
+        assert_eq!(Deck::from_deck_list(&list, lookup(&cards)).unwrap(), deck);
+    }
+
+    #[test]
+    fn deck_code_round_trip() {
+        let cards = cards();
+        let deck = legal_deck();
+        let code = deck.to_code();
+        assert!(code.starts_with("1OP01-001.4OP01-010.4OP01-011."));
+        assert!(code.ends_with(".2OP01-022"));
+
+        assert_eq!(Deck::from_code(&code, lookup(&cards)).unwrap(), deck);
+    }
+
+    #[test]
+    fn rejects_second_leader() {
+        let cards = cards();
+        let errors =
+            Deck::from_deck_list("1xOP01-001\n1xOP01-002\n4xOP01-010", lookup(&cards)).unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [DeckListError::MultipleLeaders { line: 2, card }] if card == id("OP01-002")
+        ));
+
+        let errors = Deck::from_code("2OP01-001.4OP01-010", lookup(&cards)).unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [DeckListError::MultipleLeaders { line: 1, card }] if card == id("OP01-001")
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        let cards = cards();
+        let errors = Deck::from_deck_list(
+            "1xOP01-001\n0xOP01-010\nOP01-011\n4xOP01-099\n4xOP99",
+            lookup(&cards),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [
+                DeckListError::InvalidCount { line: 2, text },
+                DeckListError::MalformedLine { line: 3, .. },
+                DeckListError::InvalidCardId { line: 5, .. },
+                DeckListError::UnknownCard { line: 4, .. },
+            ] if text == "0"
+        ));
+
+        let errors = Deck::from_code("1OP01-001.0OP01-010", lookup(&cards)).unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [DeckListError::InvalidCount { line: 2, text }] if text == "0"
+        ));
+
+        for empty in ["", "# only a comment\n\n"] {
+            assert!(matches!(
+                Deck::from_deck_list(empty, lookup(&cards)).unwrap_err()[..],
+                [DeckListError::Empty]
+            ));
+        }
+    }
+}
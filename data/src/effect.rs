@@ -0,0 +1,1568 @@
+//! Parser for the English effect and trigger text printed on cards.
+//!
+//! Card text is parsed into a list of [`Ability`]s, one per printed line. Each ability carries
+//! its bracketed keywords and timings (`[On Play]`, `[DON!! x2]`, `[Once Per Turn]`, ...), the
+//! costs written before the colon (`③`, `DON!! −1`, `You may trash 1 card from your hand`) and
+//! a body made of [`Clause`]s. Text the parser does not understand is reported as an
+//! [`EffectParseError`] rather than being silently dropped, so [`unsupported_cards`] can be used
+//! to track how much of the card pool can be driven by data.
+
+use std::{fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{CardData, CardId, CardType, Color, ParseSubtypeError, Subtype};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Effect {
+    pub abilities: Vec<Ability>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Ability {
+    pub keywords: Vec<Keyword>,
+    pub timings: Vec<Timing>,
+    /// Number of DON!! cards that must be attached for this ability to be active (`[DON!! xN]`)
+    pub don_requirement: Option<usize>,
+    pub once_per_turn: bool,
+    pub costs: Vec<Cost>,
+    pub body: Vec<Clause>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Keyword {
+    Blocker,
+    Rush,
+    DoubleAttack,
+    Banish,
+}
+
+impl Display for Keyword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Blocker => f.write_str("Blocker"),
+            Self::Rush => f.write_str("Rush"),
+            Self::DoubleAttack => f.write_str("Double Attack"),
+            Self::Banish => f.write_str("Banish"),
+        }
+    }
+}
+
+impl FromStr for Keyword {
+    type Err = EffectParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Blocker" => Ok(Self::Blocker),
+            "Rush" => Ok(Self::Rush),
+            "Double Attack" => Ok(Self::DoubleAttack),
+            "Banish" => Ok(Self::Banish),
+            other => Err(EffectParseError::UnknownKeyword(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Timing {
+    OnPlay,
+    WhenAttacking,
+    ActivateMain,
+    Main,
+    Counter,
+    Trigger,
+    OnKo,
+    OnBlock,
+    OnOpponentsAttack,
+    EndOfYourTurn,
+    YourTurn,
+    OpponentsTurn,
+}
+
+impl Display for Timing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OnPlay => f.write_str("On Play"),
+            Self::WhenAttacking => f.write_str("When Attacking"),
+            Self::ActivateMain => f.write_str("Activate: Main"),
+            Self::Main => f.write_str("Main"),
+            Self::Counter => f.write_str("Counter"),
+            Self::Trigger => f.write_str("Trigger"),
+            Self::OnKo => f.write_str("On K.O."),
+            Self::OnBlock => f.write_str("On Block"),
+            Self::OnOpponentsAttack => f.write_str("On Your Opponent's Attack"),
+            Self::EndOfYourTurn => f.write_str("End of Your Turn"),
+            Self::YourTurn => f.write_str("Your Turn"),
+            Self::OpponentsTurn => f.write_str("Opponent's Turn"),
+        }
+    }
+}
+
+impl FromStr for Timing {
+    type Err = EffectParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "On Play" => Ok(Self::OnPlay),
+            "When Attacking" => Ok(Self::WhenAttacking),
+            "Activate: Main" | "Activate:Main" => Ok(Self::ActivateMain),
+            "Main" => Ok(Self::Main),
+            "Counter" => Ok(Self::Counter),
+            "Trigger" => Ok(Self::Trigger),
+            "On K.O." => Ok(Self::OnKo),
+            "On Block" => Ok(Self::OnBlock),
+            "On Your Opponent's Attack" => Ok(Self::OnOpponentsAttack),
+            "End of Your Turn" => Ok(Self::EndOfYourTurn),
+            "Your Turn" => Ok(Self::YourTurn),
+            "Opponent's Turn" => Ok(Self::OpponentsTurn),
+            other => Err(EffectParseError::UnknownKeyword(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cost {
+    /// Rest the given number of DON!! cards in the cost area (`①`, `➁`, ...)
+    RestDon(usize),
+    /// Return the given number of DON!! cards to the DON!! deck (`DON!! −N`)
+    ReturnDon(usize),
+    TrashFromHand {
+        count: usize,
+        filter: CardFilter,
+    },
+    RestThis,
+    TrashThis,
+    LifeToHand(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Player {
+    You,
+    Opponent,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Comparison {
+    Exactly(usize),
+    AtMost(usize),
+    AtLeast(usize),
+}
+
+impl Comparison {
+    pub fn matches(&self, value: usize) -> bool {
+        match *self {
+            Self::Exactly(n) => value == n,
+            Self::AtMost(n) => value <= n,
+            Self::AtLeast(n) => value >= n,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Count {
+    Exactly(usize),
+    UpTo(usize),
+    All,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Duration {
+    ThisTurn,
+    ThisBattle,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Zone {
+    Hand,
+    Trash,
+    Deck,
+}
+
+/// Restrictions on which cards an action may select
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CardFilter {
+    pub types: Vec<CardType>,
+    pub colors: Vec<Color>,
+    pub subtypes: Vec<Subtype>,
+    pub cost: Option<Comparison>,
+    pub power: Option<Comparison>,
+    pub rested: Option<bool>,
+    /// Card names the selection is restricted to (`[Charlotte Linlin] cards`)
+    pub names: Vec<String>,
+    pub excluded_names: Vec<String>,
+    /// "other than this card"
+    pub excludes_self: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// The card this effect is printed on
+    This,
+    /// "this Leader or 1 of your Characters"
+    ThisOrCharacter,
+    Leader(Player),
+    Cards {
+        count: Count,
+        owner: Player,
+        filter: CardFilter,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    DonOnField(Comparison),
+    LifeCards(Player, Comparison),
+    HandSize(Player, Comparison),
+    TrashSize(Comparison),
+    LeaderHasSubtype(Vec<Subtype>),
+    /// `your Leader's type includes "..."`, which matches on part of a subtype's name
+    LeaderTypeIncludes(String),
+    LeaderIs(String),
+    CharacterCount(Comparison),
+    ControlsNamed(String),
+    ThisIsRested,
+    ControlsCard(CardFilter),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Ko(Target),
+    Rest(Target),
+    SetActive(Target),
+    ReturnToHand(Target),
+    PlaceAtDeckBottom(Target),
+    Trash(Target),
+    GivePower {
+        target: Target,
+        amount: isize,
+        duration: Option<Duration>,
+    },
+    GiveCost {
+        target: Target,
+        amount: isize,
+        duration: Option<Duration>,
+    },
+    GrantKeyword {
+        target: Target,
+        keyword: Keyword,
+        duration: Option<Duration>,
+    },
+    GiveDon {
+        target: Target,
+        count: Count,
+    },
+    AddDon {
+        count: Count,
+        rested: bool,
+    },
+    Draw(usize),
+    TrashFromHand {
+        player: Player,
+        count: Count,
+    },
+    TrashFromDeck(usize),
+    LifeToHand(Count),
+    DeckToLife(Count),
+    Search {
+        look: usize,
+        count: Count,
+        filter: CardFilter,
+    },
+    PlaceRestAtDeckBottom,
+    TrashRest,
+    PlayThis,
+    Play {
+        count: Count,
+        filter: CardFilter,
+        from: Zone,
+        rested: bool,
+    },
+    AddToHand {
+        count: Count,
+        filter: CardFilter,
+        from: Zone,
+    },
+    ActivateMainOfThis,
+    ChooseOne(Vec<Vec<Clause>>),
+}
+
+/// A sentence of an ability body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clause {
+    /// Whether the clause was introduced with "Then," and so only resolves after the previous one
+    pub then: bool,
+    pub optional: bool,
+    pub condition: Option<Condition>,
+    pub actions: Vec<Action>,
+}
+
+#[derive(Error, Debug)]
+pub enum EffectParseError {
+    #[error("Unknown keyword '[{0}]'")]
+    UnknownKeyword(String),
+
+    #[error("Unterminated bracket in '{0}'")]
+    Unterminated(String),
+
+    #[error("Unsupported cost '{0}'")]
+    UnsupportedCost(String),
+
+    #[error("Unsupported clause '{0}'")]
+    UnsupportedClause(String),
+
+    #[error("Option bullet without a preceding 'choose one' ability")]
+    DanglingOption,
+
+    #[error(transparent)]
+    Subtype(#[from] ParseSubtypeError),
+}
+
+impl FromStr for Effect {
+    type Err = EffectParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut abilities: Vec<Ability> = vec![];
+
+        for line in s.lines() {
+            let line = strip_reminder_text(line);
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(option) = line.strip_prefix('•') {
+                let body = parse_body(option.trim())?;
+                match abilities
+                    .last_mut()
+                    .and_then(|ability| ability.body.last_mut())
+                    .and_then(|clause| clause.actions.last_mut())
+                {
+                    Some(Action::ChooseOne(options)) => options.push(body),
+                    _ => return Err(EffectParseError::DanglingOption),
+                }
+            } else if line.starts_with("Then,") && !abilities.is_empty() {
+                let body = parse_body(line)?;
+                abilities.last_mut().unwrap().body.extend(body);
+            } else {
+                abilities.push(parse_ability(line)?);
+            }
+        }
+
+        Ok(Self { abilities })
+    }
+}
+
+impl Effect {
+    pub fn has_keyword(&self, keyword: Keyword) -> bool {
+        self.abilities
+            .iter()
+            .any(|ability| ability.keywords.contains(&keyword))
+    }
+
+    pub fn abilities_with_timing(&self, timing: Timing) -> impl Iterator<Item = &Ability> {
+        self.abilities
+            .iter()
+            .filter(move |ability| ability.timings.contains(&timing))
+    }
+}
+
+/// Parses both the effect and trigger text of a card. Cards without any text produce an empty
+/// [`Effect`].
+pub fn parse_card(card: &CardData) -> Result<Effect, EffectParseError> {
+    let mut effect = match card.effect.as_deref() {
+        Some(text) => Effect::from_str(text)?,
+        None => Effect::default(),
+    };
+
+    if let Some(trigger) = card.trigger.as_deref() {
+        effect
+            .abilities
+            .extend(Effect::from_str(trigger)?.abilities);
+    }
+
+    Ok(effect)
+}
+
+/// Parses every card and returns the ones whose text could not be fully understood, in the
+/// order they were provided.
+pub fn unsupported_cards<'a>(
+    cards: impl IntoIterator<Item = &'a CardData>,
+) -> Vec<(CardId, EffectParseError)> {
+    cards
+        .into_iter()
+        .filter_map(|card| parse_card(card).err().map(|err| (card.id, err)))
+        .collect()
+}
+
+/// Removes parenthesized reminder text such as "(This card can attack on the turn in which it
+/// is played.)". Parentheses directly attached to a word, like "card(s)", are kept.
+fn strip_reminder_text(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut depth = 0usize;
+    let mut prev = ' ';
+
+    for c in line.chars() {
+        if c == '(' && (depth > 0 || prev.is_whitespace()) {
+            depth += 1;
+        } else if c == ')' && depth > 0 {
+            depth -= 1;
+        } else if depth == 0 {
+            out.push(c);
+        }
+        prev = c;
+    }
+
+    out
+}
+
+fn parse_ability(line: &str) -> Result<Ability, EffectParseError> {
+    let mut ability = Ability::default();
+    let mut rest = line.trim_start();
+
+    while let Some(inner) = rest.strip_prefix('[') {
+        let (name, after) = inner
+            .split_once(']')
+            .ok_or_else(|| EffectParseError::Unterminated(line.to_string()))?;
+
+        if let Some(count) = name.strip_prefix("DON!! x") {
+            ability.don_requirement = Some(
+                count
+                    .parse()
+                    .map_err(|_| EffectParseError::UnknownKeyword(name.to_string()))?,
+            );
+        } else if name == "Once Per Turn" {
+            ability.once_per_turn = true;
+        } else if let Ok(keyword) = Keyword::from_str(name) {
+            ability.keywords.push(keyword);
+        } else if let Ok(timing) = Timing::from_str(name) {
+            ability.timings.push(timing);
+        } else if ability.keywords.is_empty() && ability.timings.is_empty() {
+            return Err(EffectParseError::UnknownKeyword(name.to_string()));
+        } else {
+            // A card name reference such as "[Jango]" is part of the body
+            break;
+        }
+
+        rest = after.trim_start();
+        rest = rest.strip_prefix('/').unwrap_or(rest).trim_start();
+    }
+
+    if rest.is_empty() {
+        return Ok(ability);
+    }
+
+    let (costs, body) = split_costs(rest)?;
+    ability.costs = costs;
+    ability.body = parse_body(body)?;
+    Ok(ability)
+}
+
+fn split_costs(text: &str) -> Result<(Vec<Cost>, &str), EffectParseError> {
+    let Some((costs, body)) = text.split_once(':') else {
+        return Ok((vec![], text));
+    };
+
+    // "Choose one:" introduces a list of options rather than a cost
+    if body.trim().is_empty() || costs.trim_end().ends_with("hoose one") {
+        return Ok((vec![], text));
+    }
+
+    let mut out = vec![];
+    let mut rest = costs.trim();
+
+    while !rest.is_empty() {
+        let mut chars = rest.chars();
+        if let Some(count) = chars.next().and_then(circled_number) {
+            out.push(Cost::RestDon(count));
+            rest = chars.as_str().trim_start();
+            continue;
+        }
+
+        let mut p = Cursor::new(rest);
+        if p.eat("DON!! ") && p.eat_minus() {
+            out.push(Cost::ReturnDon(p.number().ok_or_else(|| {
+                EffectParseError::UnsupportedCost(rest.to_string())
+            })?));
+            rest = p.rest.trim_start();
+            continue;
+        }
+
+        let mut p = Cursor::new(rest);
+        p.eat("You may ");
+        let cost = if p.eat("trash ") {
+            if p.eat("this Character") {
+                Some(Cost::TrashThis)
+            } else {
+                match (p.number(), p.eat(" ")) {
+                    (Some(count), true) => p
+                        .card_filter()?
+                        .filter(|_| p.eat(" from your hand"))
+                        .map(|filter| Cost::TrashFromHand { count, filter }),
+                    _ => None,
+                }
+            }
+        } else if p.eat("rest this ") {
+            p.eat_word();
+            Some(Cost::RestThis)
+        } else if p.eat("add ") {
+            p.number()
+                .filter(|_| {
+                    p.eat_card_noun() && p.eat(" from the top of your Life cards to your hand")
+                })
+                .map(Cost::LifeToHand)
+        } else {
+            None
+        };
+
+        match cost {
+            Some(cost) => {
+                out.push(cost);
+                rest = p.rest.trim_start();
+                rest = rest.strip_prefix("and ").unwrap_or(rest);
+            }
+            None => return Err(EffectParseError::UnsupportedCost(costs.trim().to_string())),
+        }
+    }
+
+    Ok((out, body.trim_start()))
+}
+
+fn circled_number(c: char) -> Option<usize> {
+    match c {
+        '①'..='⑳' => Some(c as usize - '①' as usize + 1),
+        '➀'..='➉' => Some(c as usize - '➀' as usize + 1),
+        _ => None,
+    }
+}
+
+/// Splits a body into sentences, keeping "K.O." and bracketed names intact.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut out = vec![];
+    let mut start = 0;
+    let mut depth = 0usize;
+
+    for (idx, c) in text.char_indices() {
+        match c {
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth = depth.saturating_sub(1),
+            '.' if depth == 0 => {
+                let sentence = &text[start..idx];
+                let next = text[idx + 1..].chars().next();
+                let in_ko = sentence.ends_with("K") || sentence.ends_with("K.O");
+                if !in_ko && matches!(next, None | Some(' ' | '\n')) {
+                    out.push(sentence.trim());
+                    start = idx + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let tail = text[start..].trim();
+    if !tail.is_empty() {
+        out.push(tail);
+    }
+
+    out.retain(|sentence| !sentence.is_empty());
+    out
+}
+
+fn parse_body(text: &str) -> Result<Vec<Clause>, EffectParseError> {
+    sentences(text).into_iter().map(parse_clause).collect()
+}
+
+fn parse_clause(sentence: &str) -> Result<Clause, EffectParseError> {
+    let unsupported = || EffectParseError::UnsupportedClause(sentence.to_string());
+    let mut p = Cursor::new(sentence);
+
+    let then = p.eat("Then, ") || p.eat("then, ");
+    let mut condition = None;
+
+    if p.eat_ci("if ") {
+        let (cond, rest) = p.rest.split_once(", ").ok_or_else(unsupported)?;
+        condition = Some(parse_condition(cond)?.ok_or_else(unsupported)?);
+        p.rest = rest;
+    }
+
+    let optional = p.eat_ci("you may ");
+
+    if p.eat_ci("choose one:") || p.eat_ci("choose one") {
+        if !p.rest.trim().is_empty() {
+            return Err(unsupported());
+        }
+
+        return Ok(Clause {
+            then,
+            optional,
+            condition,
+            actions: vec![Action::ChooseOne(vec![])],
+        });
+    }
+
+    let mut actions = vec![];
+    loop {
+        actions.push(parse_action(&mut p)?.ok_or_else(unsupported)?);
+
+        if p.rest.trim().is_empty() {
+            break;
+        }
+
+        if !(p.eat(" and ") || p.eat(", then ") || p.eat(" then ") || p.eat(", and ")) {
+            return Err(unsupported());
+        }
+    }
+
+    Ok(Clause {
+        then,
+        optional,
+        condition,
+        actions,
+    })
+}
+
+fn parse_condition(text: &str) -> Result<Option<Condition>, EffectParseError> {
+    let mut p = Cursor::new(text);
+
+    if p.eat("your Leader has the ") || p.eat("your Leader is the ") {
+        let subtypes = p.subtypes()?;
+        if !subtypes.is_empty() && p.eat(" type") && p.is_empty() {
+            return Ok(Some(Condition::LeaderHasSubtype(subtypes)));
+        }
+        return Ok(None);
+    }
+
+    if p.eat("your Leader's type includes \"") {
+        let Some((name, rest)) = p.rest.split_once('"') else {
+            return Ok(None);
+        };
+        p.rest = rest;
+        return Ok(p
+            .is_empty()
+            .then(|| Condition::LeaderTypeIncludes(name.to_string())));
+    }
+
+    if p.eat("your Leader is ") {
+        return Ok(p
+            .bracketed()
+            .filter(|_| p.is_empty())
+            .map(|name| Condition::LeaderIs(name.to_string())));
+    }
+
+    if p.eat("this Character is rested") || p.eat("this Leader is rested") {
+        return Ok(p.is_empty().then_some(Condition::ThisIsRested));
+    }
+
+    let player = if p.eat("your opponent has ") {
+        Player::Opponent
+    } else if p.eat("you have ") {
+        Player::You
+    } else {
+        return Ok(None);
+    };
+
+    let Some(count) = p.comparison() else {
+        if player == Player::You {
+            if let Some(name) = p.bracketed() {
+                return Ok(p
+                    .is_empty()
+                    .then(|| Condition::ControlsNamed(name.to_string())));
+            }
+        }
+
+        if player == Player::You && (p.eat("a ") || p.eat("an ")) {
+            let filter = p.card_filter()?;
+            return Ok(filter.filter(|_| p.is_empty()).map(Condition::ControlsCard));
+        }
+        return Ok(None);
+    };
+
+    let condition = if p.eat(" Life card") {
+        p.eat("s");
+        Condition::LifeCards(player, count)
+    } else if p.eat(" cards in your hand") || p.eat(" cards in their hand") {
+        Condition::HandSize(player, count)
+    } else if player == Player::You && p.eat(" cards in your trash") {
+        Condition::TrashSize(count)
+    } else if player == Player::You && p.eat(" DON!! cards on your field") {
+        Condition::DonOnField(count)
+    } else if player == Player::You && p.eat(" Character") {
+        p.eat("s");
+        Condition::CharacterCount(count)
+    } else {
+        return Ok(None);
+    };
+
+    Ok(p.is_empty().then_some(condition))
+}
+
+fn parse_action(p: &mut Cursor) -> Result<Option<Action>, EffectParseError> {
+    let start = p.rest;
+
+    if p.eat_ci("K.O. ") {
+        return Ok(p.target()?.map(Action::Ko));
+    }
+
+    if p.eat_ci("rest ") {
+        return Ok(p.target()?.map(Action::Rest));
+    }
+
+    if p.eat_ci("set ") {
+        let target = p.target()?;
+        return Ok(target
+            .filter(|_| p.eat(" as active"))
+            .map(Action::SetActive));
+    }
+
+    if p.eat_ci("return ") {
+        let target = p.target()?;
+        return Ok(target
+            .filter(|_| p.eat(" to the owner's hand") || p.eat(" to your hand"))
+            .map(Action::ReturnToHand));
+    }
+
+    if p.eat_ci("place ") {
+        if p.eat("the rest at the bottom of your deck in any order") {
+            return Ok(Some(Action::PlaceRestAtDeckBottom));
+        }
+
+        let target = p.target()?;
+        return Ok(target
+            .filter(|_| p.eat(" at the bottom of the owner's deck"))
+            .map(Action::PlaceAtDeckBottom));
+    }
+
+    if p.eat_ci("trash the rest") {
+        return Ok(Some(Action::TrashRest));
+    }
+
+    if p.eat_ci("draw ") {
+        let count = p.number();
+        return Ok(count.filter(|_| p.eat_card_noun()).map(Action::Draw));
+    }
+
+    if p.eat_ci("trash ") {
+        let mut probe = p.clone();
+        if let Some(count) = probe.count() {
+            if probe.eat_card_noun() {
+                if probe.eat(" from your hand") {
+                    *p = probe;
+                    return Ok(Some(Action::TrashFromHand {
+                        player: Player::You,
+                        count,
+                    }));
+                }
+
+                if probe.eat(" from the top of your deck") {
+                    *p = probe;
+                    return Ok(match count {
+                        Count::Exactly(n) => Some(Action::TrashFromDeck(n)),
+                        _ => None,
+                    });
+                }
+            }
+        }
+
+        return Ok(p.target()?.map(Action::Trash));
+    }
+
+    if p.eat_ci("your opponent trashes ") {
+        let count = p.count();
+        return Ok(count
+            .filter(|_| p.eat_card_noun() && p.eat(" from their hand"))
+            .map(|count| Action::TrashFromHand {
+                player: Player::Opponent,
+                count,
+            }));
+    }
+
+    if p.eat_ci("give ") {
+        let mut probe = p.clone();
+        if let Some(count) = probe.don_count() {
+            if probe.eat(" to ") {
+                *p = probe;
+                return Ok(p.target()?.map(|target| Action::GiveDon { target, count }));
+            }
+        }
+
+        let Some(target) = p.target()? else {
+            return Ok(None);
+        };
+
+        if !p.eat(" ") {
+            return Ok(None);
+        }
+
+        if let Some(count) = p.don_count() {
+            return Ok(Some(Action::GiveDon { target, count }));
+        }
+
+        let Some(amount) = p.signed_number() else {
+            return Ok(None);
+        };
+
+        let power = if p.eat(" power") {
+            true
+        } else if p.eat(" cost") {
+            false
+        } else {
+            return Ok(None);
+        };
+
+        let duration = p.duration();
+        return Ok(Some(if power {
+            Action::GivePower {
+                target,
+                amount,
+                duration,
+            }
+        } else {
+            Action::GiveCost {
+                target,
+                amount,
+                duration,
+            }
+        }));
+    }
+
+    if p.eat_ci("add ") {
+        let mut probe = p.clone();
+        if let Some(count) = probe.don_count() {
+            if probe.eat(" from your DON!! deck and ") {
+                let rested = if probe.eat("rest it") || probe.eat("rest them") {
+                    true
+                } else if probe.eat("set it as active") || probe.eat("set them as active") {
+                    false
+                } else {
+                    return Ok(None);
+                };
+
+                *p = probe;
+                return Ok(Some(Action::AddDon { count, rested }));
+            }
+        }
+
+        let mut probe = p.clone();
+        if let Some(count) = probe.count() {
+            if probe.eat_card_noun() && probe.eat(" from the top of your Life cards to your hand") {
+                *p = probe;
+                return Ok(Some(Action::LifeToHand(count)));
+            }
+        }
+
+        let mut probe = p.clone();
+        if let Some(count) = probe.count() {
+            if probe.eat_card_noun()
+                && probe.eat(" from the top of your deck to the top of your Life cards")
+            {
+                *p = probe;
+                return Ok(Some(Action::DeckToLife(count)));
+            }
+        }
+
+        let Some(count) = p.count() else {
+            return Ok(None);
+        };
+        p.eat(" ");
+        let Some(filter) = p.card_filter()? else {
+            return Ok(None);
+        };
+
+        let from = if p.eat(" from your trash to your hand") {
+            Zone::Trash
+        } else if p.eat(" from your deck to your hand") {
+            Zone::Deck
+        } else {
+            return Ok(None);
+        };
+
+        return Ok(Some(Action::AddToHand {
+            count,
+            filter,
+            from,
+        }));
+    }
+
+    if p.eat_ci("look at ") {
+        let Some(look) = p.number() else {
+            return Ok(None);
+        };
+
+        if !p.eat(" cards from the top of your deck; reveal ") {
+            return Ok(None);
+        }
+
+        let Some(count) = p.count() else {
+            return Ok(None);
+        };
+        p.eat(" ");
+        let Some(filter) = p.card_filter()? else {
+            return Ok(None);
+        };
+
+        return Ok(p.eat(" and add it to your hand").then_some(Action::Search {
+            look,
+            count,
+            filter,
+        }));
+    }
+
+    if p.eat_ci("play this card") {
+        return Ok(Some(Action::PlayThis));
+    }
+
+    if p.eat_ci("play ") {
+        let Some(count) = p.count() else {
+            return Ok(None);
+        };
+        p.eat(" ");
+        let Some(filter) = p.card_filter()? else {
+            return Ok(None);
+        };
+
+        let from = if p.eat(" from your hand") {
+            Zone::Hand
+        } else if p.eat(" from your trash") {
+            Zone::Trash
+        } else {
+            return Ok(None);
+        };
+
+        let rested = p.eat(" rested");
+        return Ok(Some(Action::Play {
+            count,
+            filter,
+            from,
+            rested,
+        }));
+    }
+
+    if p.eat_ci("activate this card's [Main] effect") {
+        return Ok(Some(Action::ActivateMainOfThis));
+    }
+
+    // "<target> gains ..."
+    if let Some(target) = p.target()? {
+        if p.eat(" gains ") || p.eat(" gain ") {
+            p.eat("an additional ");
+            if let Some(amount) = p.signed_number() {
+                if p.eat(" power") {
+                    let duration = p.duration();
+                    return Ok(Some(Action::GivePower {
+                        target,
+                        amount,
+                        duration,
+                    }));
+                }
+            } else if let Some(keyword) = p.bracketed() {
+                let keyword = Keyword::from_str(keyword)?;
+                let duration = p.duration();
+                return Ok(Some(Action::GrantKeyword {
+                    target,
+                    keyword,
+                    duration,
+                }));
+            }
+        }
+    }
+
+    p.rest = start;
+    Ok(None)
+}
+
+/// A small string cursor used by the clause parsers. Every `eat_*` method only advances when it
+/// matches, so callers can probe alternatives by cloning.
+#[derive(Clone)]
+struct Cursor<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { rest: text }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rest.trim().is_empty()
+    }
+
+    fn eat(&mut self, literal: &str) -> bool {
+        match self.rest.strip_prefix(literal) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn eat_ci(&mut self, literal: &str) -> bool {
+        match self.rest.get(..literal.len()) {
+            Some(prefix) if prefix.eq_ignore_ascii_case(literal) => {
+                self.rest = &self.rest[literal.len()..];
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn eat_minus(&mut self) -> bool {
+        self.eat("−") || self.eat("－") || self.eat("-")
+    }
+
+    fn eat_word(&mut self) -> &'a str {
+        let end = self
+            .rest
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(self.rest.len());
+        let (word, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        word
+    }
+
+    fn eat_card_noun(&mut self) -> bool {
+        self.eat(" cards") || self.eat(" card(s)") || self.eat(" card")
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let end = self
+            .rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+        let value = self.rest[..end].parse().ok()?;
+        self.rest = &self.rest[end..];
+        Some(value)
+    }
+
+    fn signed_number(&mut self) -> Option<isize> {
+        let mut probe = self.clone();
+        let sign = if probe.eat("+") {
+            1
+        } else if probe.eat_minus() {
+            -1
+        } else {
+            return None;
+        };
+
+        let value = probe.number()? as isize;
+        *self = probe;
+        Some(sign * value)
+    }
+
+    /// "up to N", "N" or "all of"
+    fn count(&mut self) -> Option<Count> {
+        if self.eat_ci("up to ") {
+            return self.number().map(Count::UpTo);
+        }
+
+        if self.eat("all of") {
+            return Some(Count::All);
+        }
+
+        self.number().map(Count::Exactly)
+    }
+
+    /// "N or less", "N or more" or "N"
+    fn comparison(&mut self) -> Option<Comparison> {
+        let mut probe = self.clone();
+        let value = probe.number()?;
+        let comparison = if probe.eat(" or less") {
+            Comparison::AtMost(value)
+        } else if probe.eat(" or more") {
+            Comparison::AtLeast(value)
+        } else {
+            Comparison::Exactly(value)
+        };
+
+        *self = probe;
+        Some(comparison)
+    }
+
+    /// "up to N rested DON!! card(s)"
+    fn don_count(&mut self) -> Option<Count> {
+        let mut probe = self.clone();
+        let count = probe.count()?;
+        probe.eat(" ");
+        probe.eat("rested ");
+        if !probe.eat("DON!!") || !probe.eat_card_noun() {
+            return None;
+        }
+
+        *self = probe;
+        Some(count)
+    }
+
+    fn duration(&mut self) -> Option<Duration> {
+        if self.eat(" during this turn") {
+            Some(Duration::ThisTurn)
+        } else if self.eat(" during this battle") {
+            Some(Duration::ThisBattle)
+        } else {
+            None
+        }
+    }
+
+    fn bracketed(&mut self) -> Option<&'a str> {
+        let inner = self.rest.strip_prefix('[')?;
+        let (name, rest) = inner.split_once(']')?;
+        self.rest = rest;
+        Some(name)
+    }
+
+    /// "{A} or {B}" (also accepts "," as a separator)
+    fn subtypes(&mut self) -> Result<Vec<Subtype>, EffectParseError> {
+        let mut out = vec![];
+
+        while let Some(inner) = self.rest.strip_prefix('{') {
+            let Some((name, rest)) = inner.split_once('}') else {
+                break;
+            };
+
+            out.push(Subtype::from_str(name)?);
+            self.rest = rest;
+
+            let mut probe = self.clone();
+            if (probe.eat(" or ") || probe.eat(", ") || probe.eat(" and "))
+                && probe.rest.starts_with('{')
+            {
+                *self = probe;
+            } else {
+                break;
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn color(&mut self) -> Option<Color> {
+        const COLORS: &[(&str, Color)] = &[
+            ("red ", Color::Red),
+            ("green ", Color::Green),
+            ("blue ", Color::Blue),
+            ("purple ", Color::Purple),
+            ("black ", Color::Black),
+            ("yellow ", Color::Yellow),
+        ];
+
+        COLORS
+            .iter()
+            .find(|(name, _)| self.eat(name))
+            .map(|(_, color)| *color)
+    }
+
+    /// Parses a description of cards such as "rested {Heart Pirates} type Character card other
+    /// than [Bepo] with a cost of 4 or less".
+    fn card_filter(&mut self) -> Result<Option<CardFilter>, EffectParseError> {
+        let mut filter = CardFilter::default();
+
+        if self.eat("rested ") {
+            filter.rested = Some(true);
+        } else if self.eat("active ") {
+            filter.rested = Some(false);
+        }
+
+        while let Some(color) = self.color() {
+            filter.colors.push(color);
+            self.eat("or ");
+        }
+
+        filter.subtypes = self.subtypes()?;
+        if !filter.subtypes.is_empty() && !self.eat(" type ") {
+            return Ok(None);
+        }
+
+        const NOUNS: &[(&str, &[CardType])] = &[
+            (
+                "Leader or Character cards",
+                &[CardType::Leader, CardType::Character],
+            ),
+            (
+                "Leader or Character card",
+                &[CardType::Leader, CardType::Character],
+            ),
+            ("Character cards", &[CardType::Character]),
+            ("Character card", &[CardType::Character]),
+            ("Characters", &[CardType::Character]),
+            ("Character", &[CardType::Character]),
+            ("Event cards", &[CardType::Event]),
+            ("Event card", &[CardType::Event]),
+            ("Event", &[CardType::Event]),
+            ("Stage cards", &[CardType::Stage]),
+            ("Stage card", &[CardType::Stage]),
+            ("Stage", &[CardType::Stage]),
+            ("cards", &[]),
+            ("card", &[]),
+        ];
+
+        while let Some(name) = self.bracketed() {
+            filter.names.push(name.to_string());
+            self.eat(",");
+            self.eat(" or ");
+            self.eat(" ");
+        }
+
+        match NOUNS.iter().find(|(noun, _)| self.eat(noun)) {
+            Some((_, types)) => filter.types = types.to_vec(),
+            None if !filter.names.is_empty() => {}
+            None => return Ok(None),
+        }
+
+        loop {
+            if self.eat(" on your field") {
+                continue;
+            }
+
+            let mut probe = self.clone();
+            if probe.eat(" other than ") {
+                if probe.eat("this card") || probe.eat("this Character") {
+                    filter.excludes_self = true;
+                    *self = probe;
+                    continue;
+                }
+
+                if let Some(name) = probe.bracketed() {
+                    filter.excluded_names.push(name.to_string());
+                    *self = probe;
+                    continue;
+                }
+            }
+
+            let mut probe = self.clone();
+            if probe.eat(" with a cost of ") {
+                if let Some(cost) = probe.comparison() {
+                    filter.cost = Some(cost);
+                    *self = probe;
+                    continue;
+                }
+            }
+
+            let mut probe = self.clone();
+            if probe.eat(" with ") {
+                if let Some(power) = probe.comparison() {
+                    if probe.eat(" power") {
+                        // "with 5000 power or less" places the comparison after the noun
+                        let power = if probe.eat(" or less") {
+                            Comparison::AtMost(comparison_value(power))
+                        } else if probe.eat(" or more") {
+                            Comparison::AtLeast(comparison_value(power))
+                        } else {
+                            power
+                        };
+                        filter.power = Some(power);
+                        *self = probe;
+                        continue;
+                    }
+                }
+            }
+
+            break;
+        }
+
+        Ok(Some(filter))
+    }
+
+    /// Parses who an action applies to, e.g. "up to 1 of your opponent's rested Characters
+    /// with a cost of 3 or less".
+    fn target(&mut self) -> Result<Option<Target>, EffectParseError> {
+        const FIXED: &[(&str, Target)] = &[
+            (
+                "this Leader or 1 of your Characters",
+                Target::ThisOrCharacter,
+            ),
+            ("this Character", Target::This),
+            ("this Leader", Target::This),
+            ("this Stage", Target::This),
+            ("this card", Target::This),
+            ("your Leader", Target::Leader(Player::You)),
+            ("your opponent's Leader", Target::Leader(Player::Opponent)),
+        ];
+
+        for (prefix, target) in FIXED {
+            let mut probe = self.clone();
+            if probe.eat_ci(prefix) && !probe.rest.starts_with(" or ") {
+                *self = probe;
+                return Ok(Some(target.clone()));
+            }
+        }
+
+        let mut probe = self.clone();
+        let Some(count) = probe.count() else {
+            return Ok(None);
+        };
+
+        let owner = if probe.eat(" of your opponent's ") {
+            Player::Opponent
+        } else if probe.eat(" of your ") {
+            Player::You
+        } else {
+            return Ok(None);
+        };
+
+        let Some(filter) = probe.card_filter()? else {
+            return Ok(None);
+        };
+
+        *self = probe;
+        Ok(Some(Target::Cards {
+            count,
+            owner,
+            filter,
+        }))
+    }
+}
+
+fn comparison_value(comparison: Comparison) -> usize {
+    match comparison {
+        Comparison::Exactly(n) | Comparison::AtMost(n) | Comparison::AtLeast(n) => n,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<Ability> {
+        Effect::from_str(text).unwrap().abilities
+    }
+
+    fn clause(actions: Vec<Action>) -> Clause {
+        Clause {
+            then: false,
+            optional: false,
+            condition: None,
+            actions,
+        }
+    }
+
+    fn opponents_characters(count: Count, filter: CardFilter) -> Target {
+        Target::Cards {
+            count,
+            owner: Player::Opponent,
+            filter: CardFilter {
+                types: vec![CardType::Character],
+                ..filter
+            },
+        }
+    }
+
+    fn your_leader_or_character() -> Target {
+        Target::Cards {
+            count: Count::UpTo(1),
+            owner: Player::You,
+            filter: CardFilter {
+                types: vec![CardType::Leader, CardType::Character],
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn on_play_with_rest_don_cost() {
+        // OP01-093
+        let abilities = parse(
+            "[On Play] ① (You may rest the specified number of DON!! cards in your cost area.): \
+             Add up to 1 DON!! card from your DON!! deck and rest it.",
+        );
+
+        assert_eq!(
+            abilities,
+            vec![Ability {
+                timings: vec![Timing::OnPlay],
+                costs: vec![Cost::RestDon(1)],
+                body: vec![clause(vec![Action::AddDon {
+                    count: Count::UpTo(1),
+                    rested: true,
+                }])],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn trash_from_hand_cost() {
+        // ST06-002
+        let abilities = parse(
+            "[On Play] You may trash 1 card from your hand: K.O. up to 1 of your opponent's \
+             Characters with a cost of 0.",
+        );
+
+        assert_eq!(
+            abilities[0].costs,
+            vec![Cost::TrashFromHand {
+                count: 1,
+                filter: CardFilter::default(),
+            }]
+        );
+        assert_eq!(
+            abilities[0].body,
+            vec![clause(vec![Action::Ko(opponents_characters(
+                Count::UpTo(1),
+                CardFilter {
+                    cost: Some(Comparison::Exactly(0)),
+                    ..Default::default()
+                },
+            ))])]
+        );
+    }
+
+    #[test]
+    fn activate_main_once_per_turn() {
+        // ST01-001
+        let abilities = parse(
+            "[Activate: Main] [Once Per Turn] Give this Leader or 1 of your Characters up to 1 \
+             rested DON!! card.",
+        );
+
+        assert_eq!(
+            abilities,
+            vec![Ability {
+                timings: vec![Timing::ActivateMain],
+                once_per_turn: true,
+                body: vec![clause(vec![Action::GiveDon {
+                    target: Target::ThisOrCharacter,
+                    count: Count::UpTo(1),
+                }])],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn don_requirement() {
+        // ST01-013
+        assert_eq!(
+            parse("[DON!! x1] This Character gains +1000 power."),
+            vec![Ability {
+                don_requirement: Some(1),
+                body: vec![clause(vec![Action::GivePower {
+                    target: Target::This,
+                    amount: 1000,
+                    duration: None,
+                }])],
+                ..Default::default()
+            }]
+        );
+
+        // ST01-004, the reminder text on its own line is dropped
+        assert_eq!(
+            parse(
+                "[DON!! x2] This Character gains [Rush].\n(This card can attack on the turn in \
+                 which it is played.)"
+            ),
+            vec![Ability {
+                don_requirement: Some(2),
+                body: vec![clause(vec![Action::GrantKeyword {
+                    target: Target::This,
+                    keyword: Keyword::Rush,
+                    duration: None,
+                }])],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn counter_then_ko() {
+        // OP01-026
+        let abilities = parse(
+            "[Counter] Up to 1 of your Leader or Character cards gains +4000 power during this \
+             battle. Then, K.O. up to 1 of your opponent's Characters with 4000 power or less.",
+        );
+
+        assert_eq!(abilities.len(), 1);
+        assert_eq!(abilities[0].timings, vec![Timing::Counter]);
+        assert_eq!(
+            abilities[0].body,
+            vec![
+                clause(vec![Action::GivePower {
+                    target: your_leader_or_character(),
+                    amount: 4000,
+                    duration: Some(Duration::ThisBattle),
+                }]),
+                Clause {
+                    then: true,
+                    ..clause(vec![Action::Ko(opponents_characters(
+                        Count::UpTo(1),
+                        CardFilter {
+                            power: Some(Comparison::AtMost(4000)),
+                            ..Default::default()
+                        },
+                    ))])
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn draw_and_trash() {
+        // ST03-005
+        let abilities =
+            parse("[DON!! x1] [When Attacking] Draw 2 cards and trash 2 cards from your hand.");
+
+        assert_eq!(abilities[0].don_requirement, Some(1));
+        assert_eq!(abilities[0].timings, vec![Timing::WhenAttacking]);
+        assert_eq!(
+            abilities[0].body,
+            vec![clause(vec![
+                Action::Draw(2),
+                Action::TrashFromHand {
+                    player: Player::You,
+                    count: Count::Exactly(2),
+                },
+            ])]
+        );
+    }
+
+    #[test]
+    fn choose_one() {
+        // ST12-006
+        let abilities = parse(
+            "[DON!! x1] [When Attacking] Choose one:\n\
+             • Rest up to 1 of your opponent's Characters with a cost of 2 or less.\n\
+             • K.O. up to 1 of your opponent's rested Characters with a cost of 2 or less.",
+        );
+
+        let cost = Some(Comparison::AtMost(2));
+        assert_eq!(abilities.len(), 1);
+        assert_eq!(
+            abilities[0].body,
+            vec![clause(vec![Action::ChooseOne(vec![
+                vec![clause(vec![Action::Rest(opponents_characters(
+                    Count::UpTo(1),
+                    CardFilter {
+                        cost,
+                        ..Default::default()
+                    },
+                ))])],
+                vec![clause(vec![Action::Ko(opponents_characters(
+                    Count::UpTo(1),
+                    CardFilter {
+                        cost,
+                        rested: Some(true),
+                        ..Default::default()
+                    },
+                ))])],
+            ])])]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_text() {
+        // ST01-002
+        let result = Effect::from_str(
+            "[DON!! x2] [When Attacking] Your opponent cannot activate a [Blocker] Character \
+             that has 5000 or more power during this battle.",
+        );
+        assert!(matches!(
+            result,
+            Err(EffectParseError::UnsupportedClause(_))
+        ));
+
+        assert!(matches!(
+            Effect::from_str("• Draw 1 card."),
+            Err(EffectParseError::DanglingOption)
+        ));
+        assert!(matches!(
+            Effect::from_str("[On Attack] Draw 1 card."),
+            Err(EffectParseError::UnknownKeyword(_))
+        ));
+    }
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use crate::CardData;
+
+/// One version of a card's effect text. Errata replace the text of a card without reprinting it,
+/// so the text printed on a card can differ from the one in effect.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TextVersion {
+    pub effect: Option<String>,
+    /// When the text came into effect, as written on the errata page. Missing for the printed
+    /// text, which is in effect from the card's release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective: Option<String>,
+    /// Link to the erratum that introduced the text, relative to the official site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errata: Option<String>,
+}
+
+impl CardData {
+    /// The effect as printed on the card. [`CardData::effect`] is always the text currently in
+    /// effect, which is what the rules should use.
+    pub fn printed_effect(&self) -> Option<&str> {
+        match self.text_history.first() {
+            Some(printed) => printed.effect.as_deref(),
+            None => self.effect.as_deref(),
+        }
+    }
+
+    /// Whether the text printed on the card is no longer the text in effect
+    pub fn is_errata(&self) -> bool {
+        self.printed_effect() != self.effect.as_deref()
+    }
+}
@@ -6,6 +6,21 @@ use std::{
 };
 use thiserror::Error;
 
+pub mod correction;
+pub mod db;
+pub mod deck;
+pub mod effect;
+pub mod errata;
+pub mod printing;
+pub mod product;
+pub mod query;
+
+pub use db::{CardDb, CardDbError};
+pub use deck::{Deck, DeckError};
+pub use errata::TextVersion;
+pub use printing::{group_printings, Printing, PrintingKind};
+pub use product::Product;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum SetId {
     Starter(usize),
@@ -64,10 +79,12 @@ impl FromStr for SetId {
             return Ok(Self::Promo);
         }
 
-        let len = s.len();
-        let sub_id = s[len - 2..].parse::<usize>()?;
+        let (Some(prefix), Some(sub_id)) = (s.get(..2), s.get(s.len().saturating_sub(2)..)) else {
+            return Err(SetIdParseError::InvalidPrefix(s.to_string()));
+        };
+        let sub_id = sub_id.parse::<usize>()?;
 
-        match &s[..2] {
+        match prefix {
             "ST" => Ok(Self::Starter(sub_id)),
             "OP" => Ok(Self::Booster(sub_id)),
             "EB" => Ok(Self::Extra(sub_id)),
@@ -129,6 +146,14 @@ impl FromStr for CardId {
             .split_once('-')
             .ok_or_else(|| CardIdParseError::ImproperForm(s.to_string()))?;
         let set_id = SetId::from_str(set_id)?;
+        // Parallel art shares the card number of its base card, e.g. `EB01-006_p1`
+        let card_id = match card_id.split_once("_p") {
+            Some((card_id, parallel)) => {
+                parallel.parse::<usize>()?;
+                card_id
+            }
+            None => card_id,
+        };
         let card_id = card_id.parse::<usize>()?;
         Ok(Self {
             set: set_id,
@@ -159,6 +184,36 @@ pub enum Rarity {
     Promo,
 }
 
+impl Rarity {
+    pub const ALL: &'static [Self] = &[
+        Self::Leader,
+        Self::Common,
+        Self::Uncommon,
+        Self::Rare,
+        Self::SuperRare,
+        Self::SecretRare,
+        Self::SpecialCard,
+        Self::TreasureRare,
+        Self::Promo,
+    ];
+}
+
+impl Display for Rarity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Leader => f.write_str("L"),
+            Self::Common => f.write_str("C"),
+            Self::Uncommon => f.write_str("UC"),
+            Self::Rare => f.write_str("R"),
+            Self::SuperRare => f.write_str("SR"),
+            Self::SecretRare => f.write_str("SEC"),
+            Self::SpecialCard => f.write_str("SP CARD"),
+            Self::TreasureRare => f.write_str("TR"),
+            Self::Promo => f.write_str("P"),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 #[error("Invalid rarity spefifier '{0}'")]
 pub struct ParseRarityError(String);
@@ -182,7 +237,7 @@ impl FromStr for Rarity {
     }
 }
 
-#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum CardType {
     Leader,
@@ -191,6 +246,10 @@ pub enum CardType {
     Event,
 }
 
+impl CardType {
+    pub const ALL: &'static [Self] = &[Self::Leader, Self::Character, Self::Stage, Self::Event];
+}
+
 #[derive(Error, Debug)]
 #[error("Invalid card type '{0}'")]
 pub struct ParseCardTypeError(String);
@@ -215,7 +274,7 @@ impl FromStr for CardType {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Color {
     Red,
     Green,
@@ -225,6 +284,17 @@ pub enum Color {
     Yellow,
 }
 
+impl Color {
+    pub const ALL: &'static [Self] = &[
+        Self::Red,
+        Self::Green,
+        Self::Blue,
+        Self::Purple,
+        Self::Black,
+        Self::Yellow,
+    ];
+}
+
 impl Display for Color {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         Debug::fmt(self, f)
@@ -445,6 +515,16 @@ pub enum Attribute {
     Wisdom,
 }
 
+impl Attribute {
+    pub const ALL: &'static [Self] = &[
+        Self::Ranged,
+        Self::Slash,
+        Self::Special,
+        Self::Strike,
+        Self::Wisdom,
+    ];
+}
+
 impl Display for Attribute {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         Debug::fmt(self, f)
@@ -470,7 +550,7 @@ impl FromStr for Attribute {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct CardData {
     pub id: CardId,
     pub release_set: SetId,
@@ -486,4 +566,18 @@ pub struct CardData {
     pub trigger: Option<String>,
     pub subtype: Vec<Subtype>,
     pub attribute: Vec<Attribute>,
+    /// Every printing of the card once rows have been merged by [`group_printings`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub printings: Vec<Printing>,
+    /// Products the printings of the card were released in, original release first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub products: Vec<Product>,
+    /// Links to the errata notices for the card, relative to the official site. The printed text
+    /// of a card with errata is no longer the text in effect.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errata: Vec<String>,
+    /// Every version of the effect text for cards with errata, from the printed text to the
+    /// current one, see [`CardData::printed_effect`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub text_history: Vec<TextVersion>,
 }
@@ -0,0 +1,247 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CardData, CardId, Rarity, SetId};
+
+/// Which version of a card a printing is. Parallel and reprint numbers come from the `_p<N>`
+/// suffix of the image name, e.g. `EB01-006_p2.png` is `Parallel(2)`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum PrintingKind {
+    Base,
+    Parallel(usize),
+    /// An alternate art released in a different set than the card itself, such as the SP cards
+    /// and promotional reprints
+    Reprint(usize),
+}
+
+/// One physical version of a card: the same [`CardId`] and rules text, but its own art, rarity and
+/// release
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Printing {
+    pub kind: PrintingKind,
+    pub release_set: SetId,
+    pub rarity: Rarity,
+    pub image_name: String,
+}
+
+impl PrintingKind {
+    /// The number in the `_p<N>` image suffix, `0` for the base printing
+    pub fn variant(&self) -> usize {
+        match self {
+            Self::Base => 0,
+            Self::Parallel(n) | Self::Reprint(n) => *n,
+        }
+    }
+}
+
+impl Printing {
+    /// The printing a single scraped row describes
+    pub fn of(card: &CardData) -> Self {
+        let stem = card
+            .image_name
+            .rsplit_once('.')
+            .map_or(card.image_name.as_str(), |(stem, _)| stem);
+        let variant = stem
+            .rsplit_once("_p")
+            .and_then(|(_, n)| n.parse::<usize>().ok())
+            .unwrap_or(0);
+
+        let kind = if variant == 0 {
+            PrintingKind::Base
+        } else if card.release_set != card.id.set {
+            PrintingKind::Reprint(variant)
+        } else {
+            PrintingKind::Parallel(variant)
+        };
+
+        Self {
+            kind,
+            release_set: card.release_set,
+            rarity: card.rarity,
+            image_name: card.image_name.clone(),
+        }
+    }
+}
+
+impl CardData {
+    /// Every printing of this card, base first. Rows that were never grouped only describe
+    /// themselves.
+    pub fn all_printings(&self) -> Vec<Printing> {
+        if self.printings.is_empty() {
+            vec![Printing::of(self)]
+        } else {
+            self.printings.clone()
+        }
+    }
+
+    pub fn printing(&self, kind: PrintingKind) -> Option<Printing> {
+        self.all_printings()
+            .into_iter()
+            .find(|printing| printing.kind == kind)
+    }
+}
+
+/// Merges rows sharing a [`CardId`] into one card per id, sorted by id. The base printing's row
+/// provides the card's `rarity`, `release_set` and `image_name`, every image becomes one entry of
+/// `printings` and the `products` and `errata` of all rows are combined. Grouping already grouped
+/// cards is a no-op.
+pub fn group_printings(cards: impl IntoIterator<Item = CardData>) -> Vec<CardData> {
+    let mut grouped: BTreeMap<CardId, CardData> = BTreeMap::new();
+
+    for mut card in cards {
+        let printings = card.all_printings();
+
+        match grouped.get_mut(&card.id) {
+            Some(existing) => {
+                let mut all = existing.all_printings();
+                all.extend(printings);
+
+                if Printing::of(existing).kind != PrintingKind::Base
+                    && Printing::of(&card).kind == PrintingKind::Base
+                {
+                    std::mem::swap(existing, &mut card);
+                }
+                existing.printings = all;
+                merge_unique(&mut existing.products, card.products);
+                merge_unique(&mut existing.errata, card.errata);
+            }
+            None => {
+                card.printings = printings;
+                grouped.insert(card.id, card);
+            }
+        }
+    }
+
+    grouped
+        .into_values()
+        .map(|mut card| {
+            // The same image can be listed by several series, even as both a parallel and a
+            // reprint, so only its first printing in kind order is kept
+            card.printings.sort_by_key(|printing| printing.kind);
+            let mut images = HashSet::new();
+            card.printings
+                .retain(|printing| images.insert(printing.image_name.clone()));
+            card
+        })
+        .collect()
+}
+
+/// Appends the items of `new` that are not in `list` yet
+fn merge_unique<T: PartialEq>(list: &mut Vec<T>, new: Vec<T>) {
+    for item in new {
+        if !list.contains(&item) {
+            list.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scraped row of OP01-001 as listed by a series released in `release_set`
+    fn row(image_name: &str, release_set: &str, rarity: &str, products: &str) -> CardData {
+        serde_json::from_str(&format!(
+            r#"{{"id":"OP01-001","release_set":"{release_set}","rarity":"{rarity}","ty":"LEADER","name":"Roronoa Zoro","image_name":"{image_name}","cost_life":5,"power":5000,"counter":null,"color":["Red"],"effect":null,"trigger":null,"subtype":[],"attribute":["Slash"],"products":[{products}],"errata":["/rules/errata_card/#errata_01"]}}"#
+        ))
+        .unwrap()
+    }
+
+    fn romance_dawn() -> String {
+        r#"{"name":"ROMANCE DAWN","set":"OP01"}"#.to_string()
+    }
+
+    fn kinds(card: &CardData) -> Vec<PrintingKind> {
+        card.printings
+            .iter()
+            .map(|printing| printing.kind)
+            .collect()
+    }
+
+    fn rows() -> Vec<CardData> {
+        vec![
+            row("OP01-001_p1.png", "OP01", "L", &romance_dawn()),
+            row(
+                "OP01-001_p2.png",
+                "P",
+                "P",
+                r#"{"name":"Tournament Pack Vol.1"}"#,
+            ),
+            row("OP01-001.png", "OP01", "L", &romance_dawn()),
+        ]
+    }
+
+    #[test]
+    fn base_row_provides_the_card_even_when_it_comes_last() {
+        let grouped = group_printings(rows());
+        assert_eq!(grouped.len(), 1);
+
+        let card = &grouped[0];
+        assert_eq!(card.image_name, "OP01-001.png");
+        assert_eq!(card.release_set, SetId::Booster(1));
+        assert_eq!(card.rarity, Rarity::Leader);
+        assert_eq!(
+            kinds(card),
+            [
+                PrintingKind::Base,
+                PrintingKind::Parallel(1),
+                PrintingKind::Reprint(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn printings_from_other_sets_are_reprints() {
+        let reprint = Printing::of(&row("OP01-001_p2.png", "P", "P", ""));
+        assert_eq!(reprint.kind, PrintingKind::Reprint(2));
+        assert_eq!(reprint.release_set, SetId::Promo);
+        assert_eq!(reprint.rarity, Rarity::Promo);
+        assert_eq!(
+            Printing::of(&row("OP01-001_p1.png", "OP01", "L", "")).kind,
+            PrintingKind::Parallel(1)
+        );
+    }
+
+    #[test]
+    fn products_and_errata_are_merged_once() {
+        let card = &group_printings(rows())[0];
+        let products: Vec<&str> = card
+            .products
+            .iter()
+            .map(|product| product.name.as_str())
+            .collect();
+        assert_eq!(products, ["ROMANCE DAWN", "Tournament Pack Vol.1"]);
+        assert_eq!(card.errata, ["/rules/errata_card/#errata_01"]);
+    }
+
+    #[test]
+    fn grouping_grouped_cards_is_a_no_op() {
+        let grouped = group_printings(rows());
+        assert_eq!(group_printings(grouped.clone()), grouped);
+
+        // Also when a grouped card meets a row it already contains
+        let mut again = grouped.clone();
+        again.extend(rows());
+        assert_eq!(group_printings(again), grouped);
+    }
+
+    #[test]
+    fn an_image_listed_by_two_series_is_kept_once() {
+        // The parallel is listed again by a later series, which makes it a reprint as well. The
+        // third parallel sorts between the two.
+        let mut rows = rows();
+        rows.push(row("OP01-001_p3.png", "OP01", "L", &romance_dawn()));
+        rows.push(row("OP01-001_p1.png", "ST01", "L", &romance_dawn()));
+        let card = &group_printings(rows)[0];
+        assert_eq!(
+            kinds(card),
+            [
+                PrintingKind::Base,
+                PrintingKind::Parallel(1),
+                PrintingKind::Parallel(3),
+                PrintingKind::Reprint(2)
+            ]
+        );
+    }
+}
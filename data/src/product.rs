@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::SetId;
+
+/// A product cards are released in, as listed under "Card Set(s)" on the official card list, e.g.
+/// `-ROMANCE DAWN- [OP01]`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Product {
+    pub name: String,
+    /// The set the product is numbered as, missing for promotional products like tournament prizes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set: Option<SetId>,
+}
+
+impl Product {
+    /// Parses the product as written on the card list. The code in brackets is written with or
+    /// without a dash depending on the region (`[OP01]`, `[OP-01]`) and the name is usually, but
+    /// not always, surrounded by dashes.
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        let (name, set) = match text
+            .strip_suffix(']')
+            .and_then(|text| text.rsplit_once('['))
+        {
+            Some((name, code)) => (name, SetId::from_str(code.trim()).ok()),
+            None => (text, None),
+        };
+
+        let name = name.trim();
+        let name = name
+            .strip_prefix('-')
+            .and_then(|name| name.strip_suffix('-'))
+            .unwrap_or(name);
+        Self {
+            name: name.trim().to_string(),
+            set,
+        }
+    }
+}
@@ -0,0 +1,481 @@
+//! Search queries over [`CardData`], e.g. `c:red/green t:character cost<=4 sub:"Straw Hat Crew"
+//! eff:blocker`.
+//!
+//! A query is a list of terms that must all match. Terms are either a bare word, which matches
+//! card names, or `<key><operator><value>`:
+//!
+//! | Key | Matches |
+//! |---|---|
+//! | `c`, `color` | any of the card's colors |
+//! | `t`, `type` | the card type |
+//! | `sub`, `subtype` | any of the card's subtypes |
+//! | `a`, `attr`, `attribute` | any of the card's attributes |
+//! | `r`, `rarity` | the rarity of the base printing |
+//! | `set` | the set of the card id |
+//! | `id` | the card id |
+//! | `cost`, `power`, `counter` | numbers, with `:`, `=`, `!=`, `<`, `<=`, `>` or `>=` |
+//! | `eff`, `effect` | a bracketed keyword or timing in the effect or trigger, e.g. `eff:"on play"` |
+//! | `o`, `text` | text in the effect or trigger |
+//! | `n`, `name` | text in the name |
+//!
+//! Values containing spaces are quoted, and several values separated by `/` match any of them.
+//! A term prefixed with `-` is negated, and `or` between terms splits the query into
+//! alternatives. Values are parsed with the `FromStr` impls of the matching type, ignoring case.
+
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::{Attribute, CardData, CardId, CardType, Color, Rarity, SetId, Subtype};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum QueryParseError {
+    #[error("Unknown search key '{0}'")]
+    UnknownKey(String),
+
+    #[error("Missing value for '{0}'")]
+    MissingValue(String),
+
+    #[error("Invalid value '{value}' for '{key}'")]
+    InvalidValue { key: String, value: String },
+
+    #[error("'{key}' can't be compared with '{operator}'")]
+    UnsupportedOperator { key: String, operator: String },
+
+    #[error("Unterminated quote in '{0}'")]
+    UnterminatedQuote(String),
+
+    #[error("'or' must be placed between two terms")]
+    DanglingOr,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    pub fn compare(&self, lhs: usize, rhs: usize) -> bool {
+        match self {
+            Self::Equal => lhs == rhs,
+            Self::NotEqual => lhs != rhs,
+            Self::Less => lhs < rhs,
+            Self::LessOrEqual => lhs <= rhs,
+            Self::Greater => lhs > rhs,
+            Self::GreaterOrEqual => lhs >= rhs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Color(Vec<Color>),
+    Type(Vec<CardType>),
+    Subtype(Vec<Subtype>),
+    Attribute(Vec<Attribute>),
+    Rarity(Vec<Rarity>),
+    Set(Vec<SetId>),
+    Id(Vec<CardId>),
+    Cost(Comparison, usize),
+    /// Never matches cards without power
+    Power(Comparison, usize),
+    /// Never matches cards without a counter value
+    Counter(Comparison, usize),
+    /// Lowercase bracketed keyword or timing, without the brackets
+    Effect(Vec<String>),
+    /// Lowercase text searched in the effect and trigger
+    Text(Vec<String>),
+    /// Lowercase text searched in the name
+    Name(Vec<String>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    pub fn matches(&self, card: &CardData) -> bool {
+        match self {
+            Self::Color(colors) => colors.iter().any(|color| card.color.contains(color)),
+            Self::Type(types) => types.contains(&card.ty),
+            Self::Subtype(subtypes) => subtypes.iter().any(|sub| card.subtype.contains(sub)),
+            Self::Attribute(attrs) => attrs.iter().any(|attr| card.attribute.contains(attr)),
+            Self::Rarity(rarities) => rarities.contains(&card.rarity),
+            Self::Set(sets) => sets.contains(&card.id.set),
+            Self::Id(ids) => ids.contains(&card.id),
+            Self::Cost(cmp, value) => cmp.compare(card.cost_life, *value),
+            Self::Power(cmp, value) => card.power.is_some_and(|power| cmp.compare(power, *value)),
+            Self::Counter(cmp, value) => card
+                .counter
+                .is_some_and(|counter| cmp.compare(counter, *value)),
+            Self::Effect(keywords) => {
+                let text = card_text(card);
+                keywords
+                    .iter()
+                    .any(|keyword| text.contains(&format!("[{keyword}]")))
+            }
+            Self::Text(needles) => {
+                let text = card_text(card);
+                needles.iter().any(|needle| text.contains(needle.as_str()))
+            }
+            Self::Name(needles) => {
+                let name = card.name.to_lowercase();
+                needles.iter().any(|needle| name.contains(needle.as_str()))
+            }
+            Self::Not(filter) => !filter.matches(card),
+        }
+    }
+}
+
+/// Lowercase effect and trigger text of a card
+fn card_text(card: &CardData) -> String {
+    let mut text = card.effect.as_deref().unwrap_or_default().to_lowercase();
+    if let Some(trigger) = card.trigger.as_deref() {
+        text.push('\n');
+        text.push_str(&trigger.to_lowercase());
+    }
+    text
+}
+
+/// A parsed search query. A card matches if it matches every filter of at least one alternative.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Query {
+    pub alternatives: Vec<Vec<Filter>>,
+}
+
+impl Query {
+    /// The empty query matches every card
+    pub fn matches(&self, card: &CardData) -> bool {
+        self.alternatives.is_empty()
+            || self
+                .alternatives
+                .iter()
+                .any(|filters| filters.iter().all(|filter| filter.matches(card)))
+    }
+
+    pub fn filter<'a>(
+        &'a self,
+        cards: impl IntoIterator<Item = &'a CardData> + 'a,
+    ) -> impl Iterator<Item = &'a CardData> + 'a {
+        cards.into_iter().filter(|card| self.matches(card))
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut alternatives = vec![];
+        let mut filters = vec![];
+
+        for term in split_terms(s)? {
+            if term.eq_ignore_ascii_case("or") {
+                if filters.is_empty() {
+                    return Err(QueryParseError::DanglingOr);
+                }
+                alternatives.push(std::mem::take(&mut filters));
+                continue;
+            }
+
+            filters.push(parse_term(&term)?);
+        }
+
+        if filters.is_empty() {
+            if !alternatives.is_empty() {
+                return Err(QueryParseError::DanglingOr);
+            }
+        } else {
+            alternatives.push(filters);
+        }
+
+        Ok(Self { alternatives })
+    }
+}
+
+/// Parses `query` and returns the matching cards, in the order they were given
+pub fn search<'a>(
+    query: &str,
+    cards: impl IntoIterator<Item = &'a CardData>,
+) -> Result<Vec<&'a CardData>, QueryParseError> {
+    let query = Query::from_str(query)?;
+    Ok(cards
+        .into_iter()
+        .filter(|card| query.matches(card))
+        .collect())
+}
+
+/// Splits on whitespace outside of quotes. Quotes are kept so values can be unquoted later.
+fn split_terms(s: &str) -> Result<Vec<String>, QueryParseError> {
+    let mut terms = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in s.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(QueryParseError::UnterminatedQuote(current));
+    }
+
+    if !current.is_empty() {
+        terms.push(current);
+    }
+
+    Ok(terms)
+}
+
+const OPERATORS: &[(&str, Option<Comparison>)] = &[
+    ("<=", Some(Comparison::LessOrEqual)),
+    (">=", Some(Comparison::GreaterOrEqual)),
+    ("!=", Some(Comparison::NotEqual)),
+    ("<", Some(Comparison::Less)),
+    (">", Some(Comparison::Greater)),
+    ("=", Some(Comparison::Equal)),
+    // `:` means equality for numbers and "contains" for everything else
+    (":", None),
+];
+
+fn parse_term(term: &str) -> Result<Filter, QueryParseError> {
+    if let Some(negated) = term.strip_prefix('-').filter(|rest| !rest.is_empty()) {
+        return Ok(Filter::Not(Box::new(parse_term(negated)?)));
+    }
+
+    let split = term
+        .char_indices()
+        .take_while(|(_, c)| *c != '"')
+        .find_map(|(index, _)| {
+            OPERATORS
+                .iter()
+                .find(|(op, _)| term[index..].starts_with(op))
+                .map(|(op, cmp)| (index, *op, *cmp))
+        });
+
+    let Some((index, operator, comparison)) = split else {
+        return Ok(Filter::Name(vec![unquote(term).to_lowercase()]));
+    };
+
+    let key = term[..index].to_lowercase();
+    let value = unquote(&term[index + operator.len()..]);
+    if value.is_empty() {
+        return Err(QueryParseError::MissingValue(key));
+    }
+
+    let invalid = || QueryParseError::InvalidValue {
+        key: key.clone(),
+        value: value.to_string(),
+    };
+
+    let number = |filter: fn(Comparison, usize) -> Filter| {
+        let value = value.parse::<usize>().map_err(|_| invalid())?;
+        Ok(filter(comparison.unwrap_or(Comparison::Equal), value))
+    };
+
+    match key.as_str() {
+        "cost" => return number(Filter::Cost),
+        "power" | "pow" => return number(Filter::Power),
+        "counter" => return number(Filter::Counter),
+        _ => {}
+    }
+
+    if comparison.is_some_and(|cmp| cmp != Comparison::Equal) {
+        return Err(QueryParseError::UnsupportedOperator {
+            key,
+            operator: operator.to_string(),
+        });
+    }
+
+    let values: Vec<&str> = value.split('/').map(str::trim).collect();
+    let lowercase = || values.iter().map(|value| value.to_lowercase()).collect();
+
+    let filter = match key.as_str() {
+        "c" | "color" => Filter::Color(parse_values(&values).ok_or_else(invalid)?),
+        "t" | "type" => Filter::Type(parse_values(&values).ok_or_else(invalid)?),
+        "sub" | "subtype" => Filter::Subtype(
+            values
+                .iter()
+                .map(|value| parse_subtype(value))
+                .collect::<Option<_>>()
+                .ok_or_else(invalid)?,
+        ),
+        "a" | "attr" | "attribute" => Filter::Attribute(parse_values(&values).ok_or_else(invalid)?),
+        "r" | "rarity" => Filter::Rarity(parse_values(&values).ok_or_else(invalid)?),
+        "set" => Filter::Set(parse_values(&values).ok_or_else(invalid)?),
+        "id" => Filter::Id(parse_values(&values).ok_or_else(invalid)?),
+        "eff" | "effect" => Filter::Effect(lowercase()),
+        "o" | "text" => Filter::Text(lowercase()),
+        "n" | "name" => Filter::Name(lowercase()),
+        _ => return Err(QueryParseError::UnknownKey(key)),
+    };
+
+    Ok(filter)
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn parse_values<T: FromStr>(values: &[&str]) -> Option<Vec<T>> {
+    values
+        .iter()
+        .map(|value| parse_ignoring_case(value))
+        .collect()
+}
+
+/// Tries the value as written, capitalized like `Red` and uppercase like `SEC`, which covers the
+/// spellings the `FromStr` impls accept
+fn parse_ignoring_case<T: FromStr>(value: &str) -> Option<T> {
+    let capitalized = value
+        .split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| {
+                    first
+                        .to_uppercase()
+                        .chain(chars.flat_map(char::to_lowercase))
+                })
+                .into_iter()
+                .flatten()
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    [value.to_string(), capitalized, value.to_uppercase()]
+        .iter()
+        .find_map(|value| T::from_str(value).ok())
+}
+
+/// Subtype names aren't consistently capitalized (`Land of Wano`, `FILM`), so fall back to
+/// comparing against every known subtype
+fn parse_subtype(value: &str) -> Option<Subtype> {
+    parse_ignoring_case(value).or_else(|| {
+        Subtype::ALL
+            .iter()
+            .copied()
+            .find(|subtype| subtype.to_string().eq_ignore_ascii_case(value))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> Result<Query, QueryParseError> {
+        Query::from_str(query)
+    }
+
+    fn card(id: &str, ty: &str, color: &str, cost: usize, effect: &str) -> CardData {
+        serde_json::from_str(&format!(
+            r#"{{"id":"{id}","release_set":"OP01","rarity":"C","ty":"{ty}","name":"{id}","image_name":"{id}.png","cost_life":{cost},"power":1000,"counter":null,"color":["{color}"],"effect":"{effect}","trigger":null,"subtype":["Straw Hat Crew"],"attribute":[]}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_terms() {
+        assert_eq!(
+            parse(r#"c:red/Green t:character cost<=4 sub:"straw hat crew" -eff:blocker luffy"#)
+                .unwrap(),
+            Query {
+                alternatives: vec![vec![
+                    Filter::Color(vec![Color::Red, Color::Green]),
+                    Filter::Type(vec![CardType::Character]),
+                    Filter::Cost(Comparison::LessOrEqual, 4),
+                    Filter::Subtype(vec![Subtype::StrawHatCrew]),
+                    Filter::Not(Box::new(Filter::Effect(vec!["blocker".to_string()]))),
+                    Filter::Name(vec!["luffy".to_string()]),
+                ]]
+            }
+        );
+        assert_eq!(parse("").unwrap(), Query::default());
+    }
+
+    #[test]
+    fn or_splits_alternatives() {
+        let cards = [
+            card(
+                "OP01-001",
+                "LEADER",
+                "Red",
+                5,
+                "[Activate: Main] Draw 1 card.",
+            ),
+            card("OP01-002", "CHARACTER", "Green", 2, "[Blocker]"),
+            card("OP01-003", "CHARACTER", "Red", 6, "[On Play] Draw 1 card."),
+        ];
+        let ids = |query| {
+            search(query, cards.iter())
+                .unwrap()
+                .into_iter()
+                .map(|card| card.id.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("t:leader or cost>5"), ["OP01-001", "OP01-003"]);
+        assert_eq!(ids(r#"c:red eff:"on play""#), ["OP01-003"]);
+        assert_eq!(ids("-eff:blocker o:draw cost:5"), ["OP01-001"]);
+        assert_eq!(ids("c:green OR c:red cost<3"), ["OP01-002"]);
+    }
+
+    #[test]
+    fn rejects_invalid_queries() {
+        assert_eq!(
+            parse("foo:bar"),
+            Err(QueryParseError::UnknownKey("foo".to_string()))
+        );
+        assert_eq!(
+            parse("c:"),
+            Err(QueryParseError::MissingValue("c".to_string()))
+        );
+        assert_eq!(
+            parse("c:red/pink"),
+            Err(QueryParseError::InvalidValue {
+                key: "c".to_string(),
+                value: "red/pink".to_string(),
+            })
+        );
+        assert_eq!(
+            parse("cost>=four"),
+            Err(QueryParseError::InvalidValue {
+                key: "cost".to_string(),
+                value: "four".to_string(),
+            })
+        );
+        assert_eq!(
+            parse("t<leader"),
+            Err(QueryParseError::UnsupportedOperator {
+                key: "t".to_string(),
+                operator: "<".to_string(),
+            })
+        );
+        assert_eq!(
+            parse(r#"sub:"Straw Hat"#),
+            Err(QueryParseError::UnterminatedQuote(
+                r#"sub:"Straw Hat"#.to_string()
+            ))
+        );
+
+        for query in ["or t:leader", "t:leader or", "t:leader or or c:red"] {
+            assert_eq!(parse(query), Err(QueryParseError::DanglingOr), "{query}");
+        }
+    }
+}
@@ -0,0 +1,431 @@
+use std::{collections::HashMap, hash::Hash, str::FromStr};
+
+use data::{
+    effect::{Keyword, Timing},
+    CardData, CardDb, CardType, Printing, PrintingKind,
+};
+use egui::{
+    text::LayoutJob, Button, CollapsingHeader, Color32, DragValue, FontId, Grid, Image, Label,
+    TextEdit, TextFormat, TextureHandle, Ui,
+};
+
+use crate::{
+    enum_combo::{EnumComboBox, EnumValue},
+    ViewerCommand, BASE_CARD_SIZE,
+};
+
+const TIMING_COLOR: Color32 = Color32::from_rgb(40, 100, 190);
+const KEYWORD_COLOR: Color32 = Color32::from_rgb(200, 110, 20);
+const DON_COLOR: Color32 = Color32::from_rgb(110, 60, 150);
+const OTHER_COLOR: Color32 = Color32::from_rgb(90, 90, 90);
+
+pub struct CardViewState {
+    pub card: CardData,
+    /// Index into the card's printings of the art being shown
+    printing: usize,
+    editing: bool,
+    /// The card as it is in the database, to tell whether there are unsaved changes
+    saved: CardData,
+    undo: Vec<CardData>,
+    redo: Vec<CardData>,
+    /// Whether the last change only touched the effect or trigger text. Consecutive text changes
+    /// are undone together instead of one keystroke at a time.
+    editing_text: bool,
+    /// Error from the last attempt to save
+    pub error: Option<String>,
+}
+
+impl CardViewState {
+    pub fn new(card: CardData) -> Self {
+        Self {
+            saved: card.clone(),
+            card,
+            printing: 0,
+            editing: false,
+            undo: vec![],
+            redo: vec![],
+            editing_text: false,
+            error: None,
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.card != self.saved
+    }
+
+    /// Called once `card` has been written to the database
+    pub fn mark_saved(&mut self, card: CardData) {
+        self.saved = card;
+        self.error = None;
+    }
+
+    fn undo(&mut self) {
+        if let Some(previous) = self.undo.pop() {
+            self.redo.push(std::mem::replace(&mut self.card, previous));
+            self.editing_text = false;
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(next) = self.redo.pop() {
+            self.undo.push(std::mem::replace(&mut self.card, next));
+            self.editing_text = false;
+        }
+    }
+
+    /// Records `before` as an undo step for the change that was just made to the card
+    fn record_change(&mut self, before: CardData) {
+        let text_only = CardData {
+            effect: self.card.effect.clone(),
+            trigger: self.card.trigger.clone(),
+            ..before.clone()
+        } == self.card;
+
+        if !(text_only && self.editing_text) {
+            self.undo.push(before);
+        }
+        self.redo.clear();
+        self.editing_text = text_only;
+    }
+
+    fn show_toolbar(&mut self, ui: &mut Ui, commands: &mut Vec<ViewerCommand>) {
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.editing, "Edit");
+
+            if self.editing {
+                let dirty = self.is_dirty();
+
+                if ui
+                    .add_enabled(!self.undo.is_empty(), Button::new("Undo"))
+                    .clicked()
+                {
+                    self.undo();
+                }
+
+                if ui
+                    .add_enabled(!self.redo.is_empty(), Button::new("Redo"))
+                    .clicked()
+                {
+                    self.redo();
+                }
+
+                if ui.add_enabled(dirty, Button::new("Revert")).clicked() {
+                    let before = self.card.clone();
+                    self.card = self.saved.clone();
+                    self.record_change(before);
+                }
+
+                if ui.add_enabled(dirty, Button::new("Save")).clicked() {
+                    commands.push(ViewerCommand::SaveCard(Box::new(self.card.clone())));
+                }
+
+                if dirty {
+                    ui.label("Unsaved changes");
+                }
+            }
+
+            if let Some(error) = &self.error {
+                ui.colored_label(Color32::RED, error);
+            }
+        });
+    }
+
+    fn show_editor(&mut self, ui: &mut Ui) {
+        let before = self.card.clone();
+        let card = &mut self.card;
+        let id = card.id;
+
+        Grid::new(("card_editor", id))
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Type");
+                EnumComboBox::from_id_source(("type", id)).show(ui, &mut card.ty);
+                ui.end_row();
+
+                ui.label("Rarity");
+                EnumComboBox::from_id_source(("rarity", id)).show(ui, &mut card.rarity);
+                ui.end_row();
+
+                ui.label(cost_label(card));
+                ui.add(DragValue::new(&mut card.cost_life).range(0..=10));
+                ui.end_row();
+
+                ui.label("Power");
+                optional_number(ui, &mut card.power);
+                ui.end_row();
+
+                ui.label("Counter");
+                optional_number(ui, &mut card.counter);
+                ui.end_row();
+
+                ui.label("Colors");
+                enum_list(ui, ("colors", id), &mut card.color);
+                ui.end_row();
+
+                ui.label("Subtypes");
+                enum_list(ui, ("subtypes", id), &mut card.subtype);
+                ui.end_row();
+
+                ui.label("Attributes");
+                enum_list(ui, ("attributes", id), &mut card.attribute);
+                ui.end_row();
+            });
+
+        optional_text(ui, "Effect", &mut card.effect);
+        optional_text(ui, "Trigger", &mut card.trigger);
+
+        // The rarity shown for the card is the one of its base printing
+        let rarity = card.rarity;
+        if let Some(base) = card
+            .printings
+            .iter_mut()
+            .find(|printing| printing.kind == PrintingKind::Base)
+        {
+            base.rarity = rarity;
+        }
+
+        if self.card != before {
+            self.record_change(before);
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        db: &CardDb,
+        images: &HashMap<String, TextureHandle>,
+        commands: &mut Vec<ViewerCommand>,
+    ) {
+        let printings = self.card.all_printings();
+        let printing = &printings[self.printing.min(printings.len() - 1)];
+
+        ui.horizontal_top(|ui| {
+            match images.get(&printing.image_name) {
+                Some(image) => {
+                    ui.add(Image::new((image.id(), BASE_CARD_SIZE * 1.5)));
+                }
+                None => {
+                    ui.allocate_ui(BASE_CARD_SIZE * 1.5, |ui| ui.spinner());
+                }
+            }
+
+            ui.vertical(|ui| {
+                ui.heading(&self.card.name);
+                self.show_toolbar(ui, commands);
+
+                if self.editing {
+                    self.show_editor(ui);
+                } else {
+                    self.show_fields(ui, printing);
+
+                    for (label, text) in [
+                        ("Effect", &self.card.effect),
+                        ("Trigger", &self.card.trigger),
+                    ] {
+                        if let Some(text) = text {
+                            ui.separator();
+                            ui.strong(label);
+                            ui.add(Label::new(highlight_keywords(ui, text)).wrap());
+                        }
+                    }
+
+                    if self.card.is_errata() {
+                        ui.separator();
+                        ui.strong("Printed effect (errata)");
+                        let printed = self.card.printed_effect().unwrap_or("-");
+                        ui.add(Label::new(highlight_keywords(ui, printed)).wrap());
+                    }
+                }
+
+                ui.separator();
+                ui.strong("Printings");
+                ui.horizontal_wrapped(|ui| {
+                    for (index, printing) in printings.iter().enumerate() {
+                        ui.selectable_value(&mut self.printing, index, printing_label(printing));
+                    }
+                });
+
+                ui.separator();
+                ui.strong("Cards sharing subtypes");
+                for subtype in self.card.subtype.iter() {
+                    let related: Vec<_> = db
+                        .by_subtype(*subtype)
+                        .filter(|card| card.id != self.card.id)
+                        .collect();
+
+                    CollapsingHeader::new(format!("{subtype} ({})", related.len()))
+                        .id_source((self.card.id, subtype.to_string()))
+                        .show(ui, |ui| {
+                            for card in related {
+                                if ui.link(format!("{} {}", card.id, card.name)).clicked() {
+                                    commands.push(ViewerCommand::OpenCard(card.id));
+                                }
+                            }
+                        });
+                }
+            });
+        });
+    }
+
+    fn show_fields(&self, ui: &mut Ui, printing: &Printing) {
+        fn list<T: ToString>(items: &[T]) -> String {
+            if items.is_empty() {
+                return "-".to_string();
+            }
+
+            items
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+
+        fn number(value: Option<usize>) -> String {
+            value.map_or_else(|| "-".to_string(), |value| value.to_string())
+        }
+
+        let card = &self.card;
+
+        Grid::new(("card_fields", card.id))
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let rows = [
+                    ("Id", card.id.to_string()),
+                    ("Set", printing.release_set.to_string()),
+                    ("Rarity", printing.rarity.to_string()),
+                    ("Type", card.ty.to_string()),
+                    (cost_label(card), card.cost_life.to_string()),
+                    ("Power", number(card.power)),
+                    ("Counter", number(card.counter)),
+                    ("Colors", list(&card.color)),
+                    ("Subtypes", list(&card.subtype)),
+                    ("Attributes", list(&card.attribute)),
+                ];
+
+                for (label, value) in rows {
+                    ui.label(label);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+fn cost_label(card: &CardData) -> &'static str {
+    if card.ty == CardType::Leader {
+        "Life"
+    } else {
+        "Cost"
+    }
+}
+
+fn optional_number(ui: &mut Ui, value: &mut Option<usize>) {
+    ui.horizontal(|ui| {
+        let mut present = value.is_some();
+        if ui.checkbox(&mut present, "").changed() {
+            *value = present.then_some(0);
+        }
+
+        if let Some(value) = value {
+            ui.add(DragValue::new(value).speed(100));
+        }
+    });
+}
+
+fn optional_text(ui: &mut Ui, label: &str, value: &mut Option<String>) {
+    ui.separator();
+    ui.horizontal(|ui| {
+        let mut present = value.is_some();
+        if ui.checkbox(&mut present, "").changed() {
+            *value = present.then(String::new);
+        }
+        ui.strong(label);
+    });
+
+    if let Some(text) = value {
+        ui.add(TextEdit::multiline(text).desired_width(f32::INFINITY));
+        ui.add(Label::new(highlight_keywords(ui, text)).wrap());
+    }
+}
+
+/// Edits a list of enum values, one combo box per entry
+fn enum_list<E: EnumValue<Variant = E> + Copy>(
+    ui: &mut Ui,
+    id_source: impl Hash + Copy,
+    items: &mut Vec<E>,
+) {
+    ui.vertical(|ui| {
+        let mut removed = None;
+        for (index, item) in items.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                EnumComboBox::from_id_source((id_source, index))
+                    .height(300.0)
+                    .show(ui, item);
+                if ui.small_button("✖").clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+
+        if let Some(index) = removed {
+            items.remove(index);
+        }
+
+        if ui.small_button("Add").clicked() {
+            items.push(E::VARIANTS[0]);
+        }
+    });
+}
+
+fn printing_label(printing: &Printing) -> String {
+    let kind = match printing.kind {
+        PrintingKind::Base => "Base".to_string(),
+        PrintingKind::Parallel(n) => format!("Parallel {n}"),
+        PrintingKind::Reprint(n) => format!("Reprint {n}"),
+    };
+
+    format!("{kind} ({}, {})", printing.rarity, printing.release_set)
+}
+
+/// Color used behind a bracketed part of the effect text, such as `[On Play]` or `[Blocker]`
+fn highlight_color(bracketed: &str) -> Color32 {
+    if Timing::from_str(bracketed).is_ok() {
+        TIMING_COLOR
+    } else if Keyword::from_str(bracketed).is_ok() {
+        KEYWORD_COLOR
+    } else if bracketed.starts_with("DON!!") {
+        DON_COLOR
+    } else {
+        OTHER_COLOR
+    }
+}
+
+fn highlight_keywords(ui: &Ui, text: &str) -> LayoutJob {
+    let font = FontId::default();
+    let plain = TextFormat::simple(font.clone(), ui.visuals().text_color());
+
+    let mut job = LayoutJob::default();
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        let Some(len) = rest[start..].find(']') else {
+            break;
+        };
+
+        let end = start + len + 1;
+        job.append(&rest[..start], 0.0, plain.clone());
+        job.append(
+            &rest[start..end],
+            0.0,
+            TextFormat {
+                background: highlight_color(&rest[start + 1..end - 1]),
+                ..TextFormat::simple(font.clone(), Color32::WHITE)
+            },
+        );
+        rest = &rest[end..];
+    }
+    job.append(rest, 0.0, plain);
+
+    job
+}
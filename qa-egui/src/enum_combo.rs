@@ -1,5 +1,6 @@
 use std::{fmt::Display, hash::Hash, marker::PhantomData};
 
+use data::{Attribute, CardType, Color, Rarity, Subtype};
 use egui::{style::WidgetVisuals, AboveOrBelow, ComboBox, Rect, TextWrapMode, Ui, WidgetText};
 
 pub trait EnumValue {
@@ -80,3 +81,26 @@ impl<E: EnumValue> EnumComboBox<E> {
             .response
     }
 }
+
+/// Implements [`EnumValue`] for fieldless enums that list their variants in an `ALL` constant
+macro_rules! impl_enum_value {
+    ($($ty:ty),*) => {
+        $(
+            impl EnumValue for $ty {
+                type Variant = Self;
+
+                const VARIANTS: &'static [Self] = <$ty>::ALL;
+
+                fn get_variant(&self) -> Self {
+                    *self
+                }
+
+                fn convert(self, new_variant: &Self) -> Self {
+                    *new_variant
+                }
+            }
+        )*
+    };
+}
+
+impl_enum_value!(Attribute, CardType, Color, Rarity, Subtype);
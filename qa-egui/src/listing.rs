@@ -0,0 +1,195 @@
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use data::{
+    query::{Query, QueryParseError},
+    CardData, CardDb, CardType, Color, Rarity, SetId, Subtype,
+};
+use egui::{Color32, ComboBox, DragValue, Ui};
+
+/// Highest cost printed on any card, used as the default upper bound of the cost filter
+const MAX_COST: usize = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SortKey {
+    #[default]
+    Id,
+    Name,
+    Cost,
+    Power,
+    Counter,
+}
+
+impl SortKey {
+    const ALL: &'static [Self] = &[Self::Id, Self::Name, Self::Cost, Self::Power, Self::Counter];
+
+    fn compare(&self, a: &CardData, b: &CardData) -> Ordering {
+        match self {
+            Self::Id => Ordering::Equal,
+            Self::Name => a.name.cmp(&b.name),
+            Self::Cost => a.cost_life.cmp(&b.cost_life),
+            Self::Power => a.power.cmp(&b.power),
+            Self::Counter => a.counter.cmp(&b.counter),
+        }
+        .then(a.id.cmp(&b.id))
+    }
+}
+
+impl Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+pub struct CardListingState {
+    /// Cards must have at least one of these colors, unless it is empty
+    pub colors: Vec<Color>,
+    pub ty: Option<CardType>,
+    pub set: Option<SetId>,
+    pub rarity: Option<Rarity>,
+    pub subtype: Option<Subtype>,
+    pub min_cost: usize,
+    pub max_cost: usize,
+    /// Searched in the name and effect, ignoring case
+    pub text: String,
+    /// Search query in the [`data::query`] syntax, applied on top of the other filters
+    pub query: String,
+    pub sort: SortKey,
+    pub descending: bool,
+}
+
+impl Default for CardListingState {
+    fn default() -> Self {
+        Self {
+            colors: vec![],
+            ty: None,
+            set: None,
+            rarity: None,
+            subtype: None,
+            min_cost: 0,
+            max_cost: MAX_COST,
+            text: String::new(),
+            query: String::new(),
+            sort: SortKey::default(),
+            descending: false,
+        }
+    }
+}
+
+fn option_combo<T: Copy + PartialEq + Display>(
+    ui: &mut Ui,
+    label: &str,
+    value: &mut Option<T>,
+    options: &[T],
+) {
+    ComboBox::from_label(label)
+        .selected_text(value.map_or_else(|| "Any".to_string(), |value| value.to_string()))
+        .height(400.0)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(value, None, "Any");
+            for option in options.iter() {
+                ui.selectable_value(value, Some(*option), option.to_string());
+            }
+        });
+}
+
+impl CardListingState {
+    fn matches(&self, card: &CardData, text: &str) -> bool {
+        (self.colors.is_empty() || self.colors.iter().any(|c| card.color.contains(c)))
+            && (self.ty.is_none() || self.ty == Some(card.ty))
+            && (self.set.is_none() || self.set == Some(card.id.set))
+            && (self.rarity.is_none() || self.rarity == Some(card.rarity))
+            && self.subtype.iter().all(|sub| card.subtype.contains(sub))
+            && (self.min_cost..=self.max_cost).contains(&card.cost_life)
+            && (text.is_empty()
+                || card.name.to_lowercase().contains(text)
+                || card
+                    .effect
+                    .as_ref()
+                    .is_some_and(|effect| effect.to_lowercase().contains(text)))
+    }
+
+    /// Cards passing every filter, in the selected order
+    pub fn results<'a>(&self, db: &'a CardDb) -> Result<Vec<&'a CardData>, QueryParseError> {
+        let query = Query::from_str(&self.query)?;
+        let text = self.text.trim().to_lowercase();
+
+        let mut results: Vec<_> = db
+            .iter()
+            .filter(|card| query.matches(card) && self.matches(card, &text))
+            .collect();
+
+        results.sort_by(|a, b| self.sort.compare(a, b));
+        if self.descending {
+            results.reverse();
+        }
+
+        Ok(results)
+    }
+
+    pub fn show_filters(&mut self, ui: &mut Ui, db: &CardDb) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Search:");
+            ui.text_edit_singleline(&mut self.text);
+
+            for color in Color::ALL.iter() {
+                let mut selected = self.colors.contains(color);
+                if ui.toggle_value(&mut selected, color.to_string()).changed() {
+                    if selected {
+                        self.colors.push(*color);
+                    } else {
+                        self.colors.retain(|c| c != color);
+                    }
+                }
+            }
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            option_combo(ui, "Type", &mut self.ty, CardType::ALL);
+            option_combo(ui, "Set", &mut self.set, &db.sets());
+            option_combo(ui, "Rarity", &mut self.rarity, Rarity::ALL);
+            option_combo(ui, "Subtype", &mut self.subtype, Subtype::ALL);
+
+            ui.label("Cost");
+            ui.add(DragValue::new(&mut self.min_cost).range(0..=self.max_cost));
+            ui.label("to");
+            ui.add(DragValue::new(&mut self.max_cost).range(self.min_cost..=MAX_COST));
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Query:");
+            ui.text_edit_singleline(&mut self.query)
+                .on_hover_text("e.g. c:red t:character cost<=4 sub:\"Straw Hat Crew\" eff:blocker");
+
+            ComboBox::from_label("Sort by")
+                .selected_text(self.sort.to_string())
+                .show_ui(ui, |ui| {
+                    for key in SortKey::ALL.iter() {
+                        ui.selectable_value(&mut self.sort, *key, key.to_string());
+                    }
+                });
+            ui.checkbox(&mut self.descending, "Descending");
+
+            if ui.button("Reset").clicked() {
+                *self = Self::default();
+            }
+        });
+    }
+
+    /// Shows the filters and the number of results, returning the cards to display
+    pub fn show<'a>(&mut self, ui: &mut Ui, db: &'a CardDb) -> Vec<&'a CardData> {
+        self.show_filters(ui, db);
+
+        let results = match self.results(db) {
+            Ok(results) => results,
+            Err(e) => {
+                ui.colored_label(Color32::RED, e.to_string());
+                vec![]
+            }
+        };
+
+        ui.label(format!("{} of {} cards", results.len(), db.len()));
+        ui.separator();
+
+        results
+    }
+}
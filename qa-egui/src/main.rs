@@ -1,6 +1,12 @@
-use std::sync::mpsc::{self, Receiver};
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver},
+};
 
-use data::{CardData, CardId};
+use data::{
+    correction::{CardPatch, Correction, Corrections},
+    CardData, CardDb, CardId, Printing, PrintingKind,
+};
 use eframe::NativeOptions;
 use egui::{
     emath::OrderedFloat, load::TexturePoll, Align, Color32, ColorImage, Layout, Sense, SizeHint,
@@ -9,30 +15,33 @@ use egui::{
 use egui_dock::{DockState, TabViewer};
 use image::GenericImageView;
 
+mod card_view;
 mod enum_combo;
+mod listing;
 
-#[derive(Default)]
-pub struct CardListingState {}
-
-pub struct CardViewState {
-    card: CardData,
-}
+use card_view::CardViewState;
+use listing::CardListingState;
 
 pub enum DbTab {
     CardListing(CardListingState),
     CardView(CardViewState),
 }
 
-enum ViewerCommand {
+pub enum ViewerCommand {
     OpenCard(CardId),
+    SaveCard(Box<CardData>),
 }
 
+const CARD_DB_PATH: &str = "cache/en/card_db.jsonl";
+const CORRECTIONS_PATH: &str = "cache/en/corrections.jsonl";
+
 const BASE_CARD_SIZE: egui::Vec2 = egui::Vec2::new(240.0, 335.0);
 const FULL_UVS: egui::Rect =
     egui::Rect::from_min_max(egui::Pos2::new(0.0, 0.0), egui::Pos2::new(1.0, 1.0));
 
 struct DbTabViewer<'a> {
-    cards: &'a [LoadedCard],
+    db: &'a CardDb,
+    images: &'a HashMap<String, TextureHandle>,
     commands: &'a mut Vec<ViewerCommand>,
 }
 
@@ -42,16 +51,23 @@ impl TabViewer for DbTabViewer<'_> {
     fn title(&mut self, tab: &mut Self::Tab) -> egui::WidgetText {
         match tab {
             DbTab::CardListing(_) => "Listing".into(),
+            DbTab::CardView(view) if view.is_dirty() => format!("{} *", view.card.id).into(),
             DbTab::CardView(view) => view.card.id.to_string().into(),
         }
     }
 
     fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
         match tab {
-            DbTab::CardListing(_) => {
+            DbTab::CardListing(state) => {
+                let results = state.show(ui, self.db);
+
                 ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
                     ui.horizontal_wrapped(|ui| {
-                        for card in self.cards.iter() {
+                        for card in results {
+                            let Some(image) = self.images.get(&card.image_name) else {
+                                continue;
+                            };
+
                             let (ui_id, rect) = ui.allocate_space(BASE_CARD_SIZE * 0.6);
                             let response = ui.interact(rect, ui_id, Sense::click());
                             let tint = if response.hovered() {
@@ -61,21 +77,21 @@ impl TabViewer for DbTabViewer<'_> {
                             };
                             let visuals = ui.style().interact(&response);
                             ui.painter().image(
-                                card.image.id(),
+                                image.id(),
                                 rect.expand(visuals.expansion),
                                 FULL_UVS,
                                 tint,
                             );
 
                             if response.clicked() {
-                                self.commands.push(ViewerCommand::OpenCard(card.data.id));
+                                self.commands.push(ViewerCommand::OpenCard(card.id));
                             }
                         }
                     });
                 });
                 // ui.image("file://./cache/en/images/ST01-001.png");
             }
-            DbTab::CardView(_) => {}
+            DbTab::CardView(view) => view.show(ui, self.db, self.images, self.commands),
         }
     }
 
@@ -86,29 +102,75 @@ impl TabViewer for DbTabViewer<'_> {
 
 pub struct DbApp {
     state: DockState<DbTab>,
-    cards: Vec<LoadedCard>,
-    rx: Receiver<LoadedCard>,
+    /// Cards as the scraper produced them, which corrections are relative to
+    scraped: CardDb,
+    /// Cards with the corrections applied
+    db: CardDb,
+    corrections: Corrections,
+    images: HashMap<String, TextureHandle>,
+    rx: Receiver<LoaderMessage>,
 }
 
 impl DbApp {
-    pub fn new(rx: Receiver<LoadedCard>) -> Self {
+    pub fn new(rx: Receiver<LoaderMessage>) -> Self {
         Self {
             state: DockState::new(vec![DbTab::CardListing(Default::default())]),
-            cards: vec![],
+            scraped: CardDb::default(),
+            db: CardDb::default(),
+            corrections: Corrections::default(),
+            images: HashMap::new(),
             rx,
         }
     }
 }
 
+impl DbApp {
+    /// Records the changes made to a card as a correction over the scraped data. Edits are not
+    /// written to `card_db.jsonl`, which the scraper regenerates, but to the corrections file,
+    /// which is replaced atomically.
+    fn save_card(&mut self, card: CardData) -> Result<(), String> {
+        let scraped = self
+            .scraped
+            .get(card.id)
+            .ok_or_else(|| format!("{} is not in the scraped database", card.id))?;
+
+        let reviewer = std::env::var("USER").unwrap_or_else(|_| "unknown".to_string());
+        self.corrections.set(Correction::new(
+            card.id,
+            reviewer,
+            CardPatch::diff(scraped, &card),
+        ));
+        self.corrections
+            .save(CORRECTIONS_PATH)
+            .map_err(|e| e.to_string())?;
+        self.db.insert(card);
+        Ok(())
+    }
+}
+
 impl eframe::App for DbApp {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         while let Ok(next) = self.rx.try_recv() {
-            self.cards.push(next);
+            match next {
+                LoaderMessage::Database {
+                    scraped,
+                    db,
+                    corrections,
+                } => {
+                    self.scraped = *scraped;
+                    self.db = *db;
+                    self.corrections = corrections;
+                }
+                LoaderMessage::Image(image_name, image) => {
+                    self.images.insert(image_name, image);
+                }
+            }
         }
 
         let mut commands = vec![];
         let mut viewer = DbTabViewer {
-            cards: &self.cards,
+            db: &self.db,
+            images: &self.images,
             commands: &mut commands,
         };
 
@@ -122,7 +184,7 @@ impl eframe::App for DbApp {
                         .iter_all_tabs()
                         .find(|(_, tab)| {
                             match tab {
-                                DbTab::CardView(CardViewState { card }) if card.id == card_id => {
+                                DbTab::CardView(view) if view.card.id == card_id => {
                                     return true;
                                 }
                                 _ => {}
@@ -137,23 +199,35 @@ impl eframe::App for DbApp {
                     }
 
                     self.state
-                        .push_to_focused_leaf(DbTab::CardView(CardViewState {
-                            card: self
-                                .cards
-                                .iter()
-                                .find(|card| card.data.id == card_id)
-                                .map(|card| card.data.clone())
-                                .unwrap(),
-                        }));
+                        .push_to_focused_leaf(DbTab::CardView(CardViewState::new(
+                            self.db.get(card_id).cloned().unwrap(),
+                        )));
+                }
+                ViewerCommand::SaveCard(card) => {
+                    let result = self.save_card((*card).clone());
+
+                    for (_, tab) in self.state.iter_all_tabs_mut() {
+                        match tab {
+                            DbTab::CardView(view) if view.card.id == card.id => match &result {
+                                Ok(()) => view.mark_saved((*card).clone()),
+                                Err(e) => view.error = Some(e.clone()),
+                            },
+                            _ => {}
+                        }
+                    }
                 }
             }
         }
     }
 }
 
-pub struct LoadedCard {
-    data: CardData,
-    image: TextureHandle,
+pub enum LoaderMessage {
+    Database {
+        scraped: Box<CardDb>,
+        db: Box<CardDb>,
+        corrections: Corrections,
+    },
+    Image(String, TextureHandle),
 }
 
 fn main() {
@@ -168,30 +242,50 @@ fn main() {
             let egui_ctx = ctx.egui_ctx.clone();
 
             std::thread::spawn(move || {
-                let card_db = std::fs::read_to_string("cache/en/card_db.jsonl").unwrap();
-
-                for card in serde_json::Deserializer::from_str(&card_db).into_iter::<CardData>() {
-                    let card = match card {
-                        Ok(card) => card,
-                        Err(e) => {
-                            eprintln!("Failed to deserialize card data: {e}");
-                            continue;
-                        }
-                    };
+                let scraped = match CardDb::load(CARD_DB_PATH) {
+                    Ok(db) => db,
+                    Err(e) => {
+                        eprintln!("Failed to load card database: {e}");
+                        return;
+                    }
+                };
 
-                    let image =
-                        image::open(format!("cache/en/images/{}", card.image_name)).unwrap();
+                let corrections = Corrections::load(CORRECTIONS_PATH).unwrap_or_else(|e| {
+                    eprintln!("Failed to load corrections: {e}");
+                    Corrections::default()
+                });
+
+                let mut card_db = scraped.clone();
+                for id in corrections.apply(&mut card_db) {
+                    eprintln!("Correction for unknown card {id} was not applied");
+                }
+
+                // Base printings first so the listing fills in before the alternate arts load
+                let mut printings: Vec<_> = card_db
+                    .iter()
+                    .flat_map(|card| card.all_printings())
+                    .collect();
+                printings.sort_by_key(|printing| printing.kind != PrintingKind::Base);
+                tx.send(LoaderMessage::Database {
+                    scraped: Box::new(scraped),
+                    db: Box::new(card_db),
+                    corrections,
+                })
+                .unwrap();
+
+                for Printing { image_name, .. } in printings {
+                    let image = image::open(format!("cache/en/images/{image_name}")).unwrap();
                     let (w, h) = image.dimensions();
                     let bytes = image.into_rgba8().into_vec();
                     let image =
                         ColorImage::from_rgba_unmultiplied([w as usize, h as usize], &bytes);
 
                     let image = egui_ctx.load_texture(
-                        format!("image#{}", card.image_name),
+                        format!("image#{image_name}"),
                         image,
                         TextureOptions::default(),
                     );
-                    tx.send(LoadedCard { data: card, image }).unwrap();
+                    tx.send(LoaderMessage::Image(image_name, image)).unwrap();
                 }
             });
 
@@ -1,6 +1,34 @@
 var currentCardId = "";
+// The card as the server last sent it, which submissions are based on
+var currentCard = null;
+
+// Fills a dropdown with the values of one of the card enums, from `/vocabulary`
+function fillOptions(id, terms) {
+  const select = document.getElementById(id);
+  select.replaceChildren();
+  for (const term of terms) {
+    const option = document.createElement("option");
+    option.value = term.value;
+    option.textContent = term.label;
+    select.appendChild(option);
+  }
+}
+
+function selectValues(id, values) {
+  for (const option of document.getElementById(id).options) {
+    option.selected = values.includes(option.value);
+  }
+}
+
+function selectedValues(id) {
+  return Array.from(document.getElementById(id).selectedOptions).map(
+    (option) => option.value,
+  );
+}
+
 function processCardUpdate(json) {
   currentCardId = json.id;
+  currentCard = json;
   document.getElementById("cardPreview").src = `images/${json.id}`;
   document.getElementById("cardname").value = json.name;
   document.getElementById("cost_life").value = json.cost_life;
@@ -49,89 +77,229 @@ function processCardUpdate(json) {
     trigger.value = "";
   }
 
-  var attributes = "";
-  for (const attribute of json.attributes) {
-    if (attributes === "") {
-      attributes = attribute;
-    } else {
-      attributes = attributes + "/" + attribute;
+  // Cards with errata keep the text that was printed, the effect above is the current text
+  const history = json.text_history ?? [];
+  const printed = history.length > 0 ? history[0].effect : json.effect;
+  document.getElementById("printed").hidden = printed === json.effect;
+  document.getElementById("printedEffect").textContent = printed ?? "-";
+
+  document.getElementById("cardkind").value = json.ty;
+  selectValues("attributes", json.attribute);
+  selectValues("colors", json.color);
+  selectValues("subtypes", json.subtype);
+  document.getElementById("status").textContent = json.status;
+  updateProgress();
+}
+
+function clearErrors() {
+  for (const element of document.getElementsByClassName("error")) {
+    element.textContent = "";
+  }
+}
+
+// Lists the changes someone else made to the card being edited. The form keeps the edits made
+// here, and submitting again saves them over the current revision.
+function showConflict(conflict) {
+  currentCard = conflict.current;
+  const error = document.getElementById("error");
+  for (const change of conflict.changes) {
+    const line = document.createElement("div");
+    const when = new Date(change.timestamp * 1000).toLocaleString();
+    const fields = Object.keys(change.to).map(
+      (field) =>
+        `${field}: ${JSON.stringify(change.from[field])} -> ${JSON.stringify(change.to[field])}`,
+    );
+    line.textContent = `${change.reviewer} (${when}) changed ${fields.join(", ")}`;
+    error.appendChild(line);
+  }
+}
+
+// Shows an error returned by the server, with field errors next to their input
+function showErrors(json) {
+  clearErrors();
+  document.getElementById("error").textContent = json.error;
+  for (const [field, message] of Object.entries(json.fields ?? {})) {
+    const element = document.getElementById(`${field}-error`);
+    if (element !== null) {
+      element.textContent = message;
     }
   }
+  if (json.conflict) {
+    showConflict(json.conflict);
+  }
+}
 
-  document.getElementById("attributes").value = attributes;
+// Fetches a card and shows it, or shows the error the server returned instead
+function fetchCard(url, options) {
+  return fetch(url, options).then((response) =>
+    response.json().then((json) => {
+      if (response.ok) {
+        clearErrors();
+        processCardUpdate(json);
+      } else {
+        showErrors(json);
+      }
+    }),
+  );
+}
 
-  var colors = "";
-  for (const color of json.colors) {
-    if (colors === "") {
-      colors = color;
+function updateProgress() {
+  fetch("/progress").then((response) =>
+    response.json().then((progress) => {
+      var text = "";
+      for (const [set, counts] of Object.entries(progress)) {
+        const reviewed = counts.total - counts.unreviewed;
+        text += `${set}: ${reviewed}/${counts.total} reviewed (${counts.verified} verified, ${counts.needs_fix} need fixes, ${counts.fixed} fixed)\n`;
+      }
+      document.getElementById("progress").textContent = text;
+    }),
+  );
+}
+
+function setReviewStatus(status) {
+  fetch(`/review/${currentCardId}`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({
+      status: status,
+      reviewer: document.getElementById("reviewer").value,
+    }),
+  }).then((response) => {
+    if (response.ok) {
+      clearErrors();
+      document.getElementById("status").textContent = status;
+      updateProgress();
     } else {
-      colors = colors + "/" + color;
+      response.json().then(showErrors);
     }
-  }
+  });
+}
 
-  document.getElementById("colors").value = colors;
+// The current card with the values of the form, as it is submitted
+function formCard() {
+  const card = {
+    ...currentCard,
+    name: document.getElementById("cardname").value,
+    ty: document.getElementById("cardkind").value,
+    subtype: selectedValues("subtypes"),
+    color: selectedValues("colors"),
+    attribute: selectedValues("attributes"),
+    cost_life: Number(document.getElementById("cost_life").value),
+    power: document.getElementById("haspower").checked
+      ? Number(document.getElementById("power").value)
+      : null,
+    counter: document.getElementById("hascounter").checked
+      ? Number(document.getElementById("counter").value)
+      : null,
+    effect: document.getElementById("haseffect").checked
+      ? document.getElementById("effect").value
+      : null,
+    trigger: document.getElementById("hastrigger").checked
+      ? document.getElementById("trigger").value
+      : null,
+  };
+  delete card.status;
+  delete card.revision;
+  return card;
+}
+
+// Whether the form shows the same values as the card sent by the server
+function formMatches(card) {
+  const saved = { ...card };
+  delete saved.status;
+  delete saved.revision;
+  return JSON.stringify(formCard()) === JSON.stringify(saved);
+}
 
-  var subtypes = "";
-  for (const subtype of json.subtypes) {
-    if (subtypes === "") {
-      subtypes = subtype;
+// Keeps the page up to date with the changes other reviewers make, see `events.rs`
+function listenForUpdates() {
+  const events = new EventSource("/events");
+
+  events.addEventListener("card", (message) => {
+    const { card, reviewer } = JSON.parse(message.data);
+    if (card.id === currentCardId && card.revision !== currentCard.revision) {
+      if (formMatches(card)) {
+        // Usually the change submitted from this page
+        processCardUpdate(card);
+      } else if (!formMatches(currentCard)) {
+        // Submitting will report the conflict along with the changes
+        document.getElementById("error").textContent =
+          `${reviewer} changed this card while you were editing it`;
+      } else {
+        processCardUpdate(card);
+        document.getElementById("error").textContent =
+          `${reviewer} changed this card`;
+      }
     } else {
-      subtypes = subtypes + "/" + subtype;
+      updateProgress();
     }
-  }
+  });
 
-  document.getElementById("subtypes").value = subtypes;
+  events.addEventListener("review", (message) => {
+    const { id, status } = JSON.parse(message.data);
+    if (id === currentCardId) {
+      currentCard.status = status;
+      document.getElementById("status").textContent = status;
+    }
+    updateProgress();
+  });
 }
 
 document.addEventListener("DOMContentLoaded", async function (_) {
-  fetch("http://localhost:8080/start").then((response) =>
-    response.json().then(processCardUpdate),
+  var reviewer = document.getElementById("reviewer");
+  reviewer.value = localStorage.getItem("reviewer") ?? "";
+  reviewer.onchange = function () {
+    localStorage.setItem("reviewer", reviewer.value);
+  };
+
+  const vocabulary = await fetch("/vocabulary").then((response) =>
+    response.json(),
+  );
+  fillOptions("cardkind", vocabulary.types);
+  fillOptions("attributes", vocabulary.attributes);
+  fillOptions("colors", vocabulary.colors);
+  fillOptions("subtypes", vocabulary.subtypes);
+
+  fetchCard(
+    `/start?reviewer=${encodeURIComponent(reviewer.value)}`,
   );
+  listenForUpdates();
 
   document.getElementById("prevCard").onclick = function () {
-    fetch(`http://localhost:8080/prev/${currentCardId}`).then((response) =>
-      response.json().then(processCardUpdate),
-    );
+    fetchCard(`/prev/${currentCardId}`);
   };
 
   document.getElementById("submit").onclick = function () {
-    const subtypes = document.getElementById("subtypes").value.split("/");
-    const colors = document.getElementById("colors").value.split("/");
-    const attributes = document.getElementById("attributes").value.split("/");
-    const body = JSON.stringify({
-      id: currentCardId,
-      name: document.getElementById("cardname").value,
-      ty: document.getElementById("cardkind").value,
-      subtypes: subtypes,
-      colors: colors,
-      attributes: attributes,
-      cost_life: document.getElementById("cost_life").value,
-      power: document.getElementById("haspower").checked
-        ? document.getElementById("power")
-        : null,
-      counter: document.getElementById("hascounter").checked
-        ? document.getElementById("counter")
-        : null,
-      effect: document.getElementById("haseffect").checked
-        ? document.getElementById("effect").value
-        : null,
-      trigger: document.getElementById("hastrigger").checked
-        ? document.getElementById("trigger").value
-        : null,
-    });
-
-    fetch(`http://localhost:8080/submit`, {
+    const card = formCard();
+    const reviewer = encodeURIComponent(
+      document.getElementById("reviewer").value,
+    );
+    fetchCard(`/submit?reviewer=${reviewer}&revision=${currentCard.revision}`, {
       method: "POST",
-      body: body,
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify(card),
     });
   };
 
-  document.getElementById("nextCard").onclick = function () {
-    fetch(`http://localhost:8080/next/${currentCardId}`).then((response) =>
-      response.json().then(processCardUpdate),
+  document.getElementById("markVerified").onclick = function () {
+    setReviewStatus("verified");
+  };
+
+  document.getElementById("markNeedsFix").onclick = function () {
+    setReviewStatus("needs-fix");
+  };
+
+  document.getElementById("nextUnreviewed").onclick = function () {
+    const set = document.getElementById("reviewset").value;
+    fetchCard(
+      `/next_unreviewed/${currentCardId}?set=${encodeURIComponent(set)}`,
     );
   };
 
+  document.getElementById("nextCard").onclick = function () {
+    fetchCard(`/next/${currentCardId}`);
+  };
+
   document.getElementById("haspower").onchange = function () {
     var has_power = document.getElementById("haspower").checked;
     var power = document.getElementById("power");
@@ -0,0 +1,167 @@
+//! JSON API over the card database, for tools that would otherwise parse the JSONL files.
+//! Cards are returned with the corrections applied.
+
+use std::{fmt::Display, str::FromStr};
+
+use axum::{
+    extract::{rejection::JsonRejection, Path, Query, State},
+    Json,
+};
+use data::{
+    correction::CardPatch, query::Query as CardQuery, Attribute, CardData, CardType, Color, Rarity,
+    SetId, Subtype,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ApiError, parse_body, parse_card_id, save_card, CardMetadata, EditParams, SharedState,
+};
+
+/// Page size used when a request does not ask for one
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct CardsParams {
+    /// Search query in the [`data::query`] syntax
+    #[serde(default)]
+    pub q: String,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct CardPage {
+    /// Number of cards matching the query, across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub cards: Vec<CardMetadata>,
+}
+
+pub async fn list_cards(
+    State(state): State<SharedState>,
+    Query(params): Query<CardsParams>,
+) -> Result<Json<CardPage>, ApiError> {
+    let query = CardQuery::from_str(&params.q).map_err(ApiError::InvalidQuery)?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    let state = state.read().unwrap();
+    let matching: Vec<&CardData> = state.db.search(&query).collect();
+    let cards = matching
+        .iter()
+        .skip(params.offset)
+        .take(limit)
+        .map(|card| CardMetadata::new(&state, card))
+        .collect();
+
+    Ok(Json(CardPage {
+        total: matching.len(),
+        offset: params.offset,
+        limit,
+        cards,
+    }))
+}
+
+pub async fn get_card(
+    Path(id): Path<String>,
+    State(state): State<SharedState>,
+) -> Result<Json<CardMetadata>, ApiError> {
+    let id = parse_card_id(&id)?;
+    let state = state.read().unwrap();
+    let card = state.db.get(id).ok_or(ApiError::UnknownCard(id))?;
+    Ok(Json(CardMetadata::new(&state, card)))
+}
+
+/// Changes the fields given in the body, which is a [`CardPatch`]. The change is saved as a
+/// correction like the ones made through the review page, and has to give the revision of the card
+/// it is based on in the query.
+pub async fn patch_card(
+    Path(id): Path<String>,
+    State(state): State<SharedState>,
+    Query(params): Query<EditParams>,
+    patch: Result<Json<serde_json::Value>, JsonRejection>,
+) -> Result<Json<CardMetadata>, ApiError> {
+    let patch: CardPatch = parse_body(patch)?;
+    let id = parse_card_id(&id)?;
+    let card = save_card(&state, id, &params, |card| patch.apply(card)).await?;
+    Ok(Json(card))
+}
+
+#[derive(Serialize)]
+pub struct SetSummary {
+    pub id: SetId,
+    pub cards: usize,
+}
+
+pub async fn list_sets(State(state): State<SharedState>) -> Json<Vec<SetSummary>> {
+    let state = state.read().unwrap();
+    let sets = state
+        .db
+        .sets()
+        .into_iter()
+        .map(|id| SetSummary {
+            id,
+            cards: state.db.by_set(id).count(),
+        })
+        .collect();
+    Json(sets)
+}
+
+pub async fn list_set_cards(
+    Path(set): Path<String>,
+    State(state): State<SharedState>,
+) -> Result<Json<Vec<CardMetadata>>, ApiError> {
+    let set = SetId::from_str(&set).map_err(|source| ApiError::InvalidSet { set, source })?;
+    let state = state.read().unwrap();
+    let cards: Vec<CardMetadata> = state
+        .db
+        .by_set(set)
+        .map(|card| CardMetadata::new(&state, card))
+        .collect();
+    if cards.is_empty() {
+        return Err(ApiError::UnknownSet(set));
+    }
+    Ok(Json(cards))
+}
+
+pub async fn list_subtypes() -> Json<&'static [Subtype]> {
+    Json(Subtype::ALL)
+}
+
+/// A value of one of the card enums, as it is serialized and as it is displayed
+#[derive(Serialize)]
+pub struct Term<T> {
+    pub value: T,
+    pub label: String,
+}
+
+fn terms<T: Copy + Display>(all: &[T]) -> Vec<Term<T>> {
+    all.iter()
+        .map(|value| Term {
+            value: *value,
+            label: value.to_string(),
+        })
+        .collect()
+}
+
+/// Every value the card enums can take, for the front end's dropdowns
+#[derive(Serialize)]
+pub struct Vocabulary {
+    pub types: Vec<Term<CardType>>,
+    pub rarities: Vec<Term<Rarity>>,
+    pub colors: Vec<Term<Color>>,
+    pub attributes: Vec<Term<Attribute>>,
+    pub subtypes: Vec<Term<Subtype>>,
+}
+
+pub async fn get_vocabulary() -> Json<Vocabulary> {
+    Json(Vocabulary {
+        types: terms(CardType::ALL),
+        rarities: terms(Rarity::ALL),
+        colors: terms(Color::ALL),
+        attributes: terms(Attribute::ALL),
+        subtypes: terms(Subtype::ALL),
+    })
+}
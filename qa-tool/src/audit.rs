@@ -0,0 +1,142 @@
+//! Log of every change made to a card through the QA tool. Each change bumps the card's revision,
+//! which writers have to send back so edits based on an outdated card can be rejected.
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use data::{correction::CardPatch, CardData, CardId};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AuditError {
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+
+    #[error("Failed to write {0}: {1}")]
+    Write(PathBuf, #[source] std::io::Error),
+
+    #[error("Invalid audit entry on line {line}: {source}")]
+    Parse {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: CardId,
+    /// Revision of the card after the change
+    pub revision: u64,
+    pub reviewer: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Previous values of the changed fields
+    pub from: CardPatch,
+    /// New values of the changed fields
+    pub to: CardPatch,
+}
+
+/// The audit log, kept in memory and appended to a JSONL file
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    entries: Vec<AuditEntry>,
+    revisions: HashMap<CardId, u64>,
+}
+
+impl AuditLog {
+    /// Loads the log at `path`, which is created on the first change if it does not exist
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AuditError> {
+        let path = path.as_ref().to_path_buf();
+        let jsonl = match std::fs::read_to_string(&path) {
+            Ok(jsonl) => jsonl,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(AuditError::Io(path, e)),
+        };
+
+        let mut log = Self {
+            path,
+            entries: vec![],
+            revisions: HashMap::new(),
+        };
+        for (index, line) in jsonl.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: AuditEntry =
+                serde_json::from_str(line).map_err(|source| AuditError::Parse {
+                    line: index + 1,
+                    source,
+                })?;
+            log.revisions.insert(entry.id, entry.revision);
+            log.entries.push(entry);
+        }
+
+        Ok(log)
+    }
+
+    /// Current revision of a card. Cards that were never changed are at revision 0.
+    pub fn revision(&self, id: CardId) -> u64 {
+        self.revisions.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Changes made to a card after the given revision, oldest first
+    pub fn changes_since(&self, id: CardId, revision: u64) -> Vec<AuditEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.id == id && entry.revision > revision)
+            .cloned()
+            .collect()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The entry recording the change from `before` to `after`, or `None` if the editable fields
+    /// did not change. The entry still has to be written with [`AuditLog::append`] and then added
+    /// with [`AuditLog::push`].
+    pub fn entry(&self, reviewer: &str, before: &CardData, after: &CardData) -> Option<AuditEntry> {
+        let to = CardPatch::diff(before, after);
+        if to.is_empty() {
+            return None;
+        }
+
+        Some(AuditEntry {
+            id: before.id,
+            revision: self.revision(before.id) + 1,
+            reviewer: reviewer.to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            from: CardPatch::diff(after, before),
+            to,
+        })
+    }
+
+    /// Appends an entry to the log file at `path`. This doesn't need the log itself, so the file
+    /// can be written without holding on to the state.
+    pub fn append(path: &Path, entry: &AuditEntry) -> Result<(), AuditError> {
+        let mut line = serde_json::to_string(entry).unwrap();
+        line.push('\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| AuditError::Write(path.to_path_buf(), e))
+    }
+
+    /// Adds an entry that was written to the file, bumping the card's revision
+    pub fn push(&mut self, entry: AuditEntry) {
+        self.revisions.insert(entry.id, entry.revision);
+        self.entries.push(entry);
+    }
+}
@@ -0,0 +1,181 @@
+//! Command line options of the QA tool. Options can also be read from a JSON config file given
+//! with `--config`, with the command line taking precedence over the file.
+
+use std::{
+    fmt::Display,
+    net::{AddrParseError, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+pub const USAGE: &str = "\
+Usage: qa-tool [OPTIONS]
+
+Options:
+    --config <FILE>     JSON file with any of the options below, e.g. {\"region\": \"jp\"}
+    --region <REGION>   Regional database to review, 'en' or 'jp' [default: en]
+    --db <FILE>         Card database [default: cache/<region>/card_db.jsonl]
+    --images <DIR>      Card images [default: cache/<region>/images]
+    --assets <DIR>      Front end files [default: qa-tool/assets]
+    --listen <ADDR>     Address to serve on [default: 0.0.0.0:8080]
+    -h, --help          Print this message";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("{USAGE}")]
+    Help,
+
+    #[error("Unknown option '{0}'")]
+    UnknownOption(String),
+
+    #[error("Missing value for {0}")]
+    MissingValue(String),
+
+    #[error("Invalid region '{0}', expected 'en' or 'jp'")]
+    InvalidRegion(String),
+
+    #[error("Invalid listen address '{0}': {1}")]
+    InvalidAddress(String, #[source] AddrParseError),
+
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+
+    #[error("Invalid config file {0}: {1}")]
+    Parse(PathBuf, #[source] serde_json::Error),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Region {
+    #[default]
+    En,
+    Jp,
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::En => f.write_str("en"),
+            Self::Jp => f.write_str("jp"),
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            s if s.eq_ignore_ascii_case("en") => Ok(Self::En),
+            s if s.eq_ignore_ascii_case("jp") => Ok(Self::Jp),
+            other => Err(ConfigError::InvalidRegion(other.to_string())),
+        }
+    }
+}
+
+/// The options that were given, either on the command line or in the config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Options {
+    region: Option<Region>,
+    db: Option<PathBuf>,
+    images: Option<PathBuf>,
+    assets: Option<PathBuf>,
+    listen: Option<SocketAddr>,
+}
+
+impl Options {
+    fn load(path: &Path) -> Result<Self, ConfigError> {
+        let json =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        serde_json::from_str(&json).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+    }
+
+    /// Fills in the options missing from `self` with the ones from `other`
+    fn or(self, other: Self) -> Self {
+        Self {
+            region: self.region.or(other.region),
+            db: self.db.or(other.db),
+            images: self.images.or(other.images),
+            assets: self.assets.or(other.assets),
+            listen: self.listen.or(other.listen),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub region: Region,
+    pub db: PathBuf,
+    pub images: PathBuf,
+    pub assets: PathBuf,
+    pub listen: SocketAddr,
+}
+
+impl Config {
+    /// Parses the command line arguments, without the program name
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, ConfigError> {
+        let mut args = args.into_iter();
+        let mut options = Options::default();
+        let mut config_file = None;
+
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                return Err(ConfigError::Help);
+            }
+
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| ConfigError::MissingValue(arg.clone()))
+            };
+            match arg.as_str() {
+                "--config" => config_file = Some(PathBuf::from(value()?)),
+                "--region" => options.region = Some(Region::from_str(&value()?)?),
+                "--db" => options.db = Some(PathBuf::from(value()?)),
+                "--images" => options.images = Some(PathBuf::from(value()?)),
+                "--assets" => options.assets = Some(PathBuf::from(value()?)),
+                "--listen" => {
+                    let addr = value()?;
+                    let listen = SocketAddr::from_str(&addr)
+                        .map_err(|e| ConfigError::InvalidAddress(addr, e))?;
+                    options.listen = Some(listen);
+                }
+                _ => return Err(ConfigError::UnknownOption(arg)),
+            }
+        }
+
+        if let Some(path) = config_file {
+            options = options.or(Options::load(&path)?);
+        }
+
+        let region = options.region.unwrap_or_default();
+        let cache = Path::new("cache").join(region.to_string());
+        Ok(Self {
+            region,
+            db: options.db.unwrap_or_else(|| cache.join("card_db.jsonl")),
+            images: options.images.unwrap_or_else(|| cache.join("images")),
+            assets: options
+                .assets
+                .unwrap_or_else(|| PathBuf::from("qa-tool/assets")),
+            listen: options
+                .listen
+                .unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 8080))),
+        })
+    }
+
+    /// Corrections are stored next to the database they apply to
+    pub fn corrections_path(&self) -> PathBuf {
+        self.db.with_file_name("corrections.jsonl")
+    }
+
+    pub fn reviews_path(&self) -> PathBuf {
+        self.db.with_file_name("reviews.json")
+    }
+
+    pub fn audit_path(&self) -> PathBuf {
+        self.db.with_file_name("audit.jsonl")
+    }
+}
@@ -0,0 +1,191 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use axum::{
+    extract::rejection::JsonRejection,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use data::{
+    correction::CorrectionError, query::QueryParseError, CardId, CardIdParseError, SetId,
+    SetIdParseError,
+};
+use serde::Serialize;
+use serde_path_to_error::Segment;
+use thiserror::Error;
+
+use crate::{
+    audit::{AuditEntry, AuditError},
+    review::ReviewError,
+    CardMetadata,
+};
+
+/// Problems with the submitted fields, keyed by the name of the field in the request
+pub type FieldErrors = BTreeMap<String, String>;
+
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("Invalid card id '{id}': {source}")]
+    InvalidCardId {
+        id: String,
+        #[source]
+        source: CardIdParseError,
+    },
+
+    #[error("Invalid set '{set}': {source}")]
+    InvalidSet {
+        set: String,
+        #[source]
+        source: SetIdParseError,
+    },
+
+    #[error("Invalid search query: {0}")]
+    InvalidQuery(#[source] QueryParseError),
+
+    #[error("Invalid request body: {}", .0.body_text())]
+    InvalidBody(#[from] JsonRejection),
+
+    #[error("Invalid request body: {0}")]
+    InvalidData(#[from] serde_path_to_error::Error<serde_json::Error>),
+
+    #[error("Invalid value for {}", .0.keys().cloned().collect::<Vec<_>>().join(", "))]
+    InvalidFields(FieldErrors),
+
+    #[error("Unknown card {0}")]
+    UnknownCard(CardId),
+
+    #[error("No cards in set {0}")]
+    UnknownSet(SetId),
+
+    #[error("Card {} was changed by someone else", .0.current.card.id)]
+    Conflict(Box<Conflict>),
+
+    #[error("Every card has been reviewed")]
+    NoUnreviewedCards,
+
+    #[error("File not found: {0}")]
+    FileNotFound(String),
+
+    #[error("Failed to read {0}: {1}")]
+    Read(PathBuf, #[source] std::io::Error),
+
+    #[error(transparent)]
+    Correction(#[from] CorrectionError),
+
+    #[error(transparent)]
+    Review(#[from] ReviewError),
+
+    #[error(transparent)]
+    Audit(#[from] AuditError),
+}
+
+/// An edit based on an outdated revision of a card
+#[derive(Serialize, Debug)]
+pub struct Conflict {
+    /// The card as it is now, to base a new edit on
+    pub current: CardMetadata,
+    /// Changes made since the revision the edit was based on
+    pub changes: Vec<AuditEntry>,
+}
+
+/// The JSON body of error responses
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    fields: FieldErrors,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conflict: Option<Box<Conflict>>,
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidCardId { .. }
+            | Self::InvalidSet { .. }
+            | Self::InvalidQuery(_)
+            | Self::InvalidBody(_)
+            | Self::InvalidData(_)
+            | Self::InvalidFields(_) => StatusCode::BAD_REQUEST,
+            Self::UnknownCard(_)
+            | Self::UnknownSet(_)
+            | Self::NoUnreviewedCards
+            | Self::FileNotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::Read(..) | Self::Correction(_) | Self::Review(_) | Self::Audit(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    fn fields(&self) -> FieldErrors {
+        let field = |name: &str, message: String| FieldErrors::from([(name.to_string(), message)]);
+        match self {
+            Self::InvalidCardId { source, .. } => field("id", source.to_string()),
+            Self::InvalidSet { source, .. } => field("set", source.to_string()),
+            Self::InvalidQuery(source) => field("q", source.to_string()),
+            // Only errors in a top level field can be shown next to its input
+            Self::InvalidData(error) => match error.path().iter().next() {
+                Some(Segment::Map { key }) => field(key, error.inner().to_string()),
+                _ => FieldErrors::new(),
+            },
+            Self::InvalidFields(fields) => fields.clone(),
+            _ => FieldErrors::new(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let mut body = ErrorBody {
+            error: self.to_string(),
+            fields: self.fields(),
+            conflict: None,
+        };
+        if let Self::Conflict(conflict) = self {
+            body.conflict = Some(conflict);
+        }
+        (status, Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data::correction::CardPatch;
+    use serde_json::json;
+
+    use super::*;
+    use crate::parse_body;
+
+    fn patch_error(body: serde_json::Value) -> ApiError {
+        parse_body::<CardPatch>(Ok(Json(body))).unwrap_err()
+    }
+
+    #[test]
+    fn invalid_values_are_reported_by_field() {
+        let error = patch_error(json!({ "power": "lots" }));
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        let fields = error.fields();
+        assert_eq!(fields.keys().collect::<Vec<_>>(), ["power"]);
+        assert!(fields["power"].contains("invalid type"), "{fields:?}");
+
+        let fields = patch_error(json!({ "name": "Nami", "color": ["Red", "Rde"] })).fields();
+        assert_eq!(fields.keys().collect::<Vec<_>>(), ["color"]);
+        assert!(
+            fields["color"].contains("unknown variant `Rde`"),
+            "{fields:?}"
+        );
+    }
+
+    #[test]
+    fn invalid_bodies_have_no_field() {
+        let error = patch_error(json!(["not", "a", "patch"]));
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        assert!(error.fields().is_empty());
+        assert!(
+            error.to_string().starts_with("Invalid request body"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Live updates for the connected pages, sent as server-sent events whenever a card or its review
+//! status changes so every reviewer sees the same data without reloading.
+
+use std::{convert::Infallible, time::Duration};
+
+use axum::{
+    extract::State,
+    response::sse::{self, KeepAlive, Sse},
+};
+use data::CardId;
+use futures_util::{stream, Stream};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{review::ReviewStatus, CardMetadata, SharedState};
+
+/// Number of events kept for clients that are slow to receive them. Clients further behind miss
+/// the oldest events.
+const CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Event {
+    /// A card was edited, sent as the `card` event
+    Card {
+        card: Box<CardMetadata>,
+        reviewer: String,
+    },
+    /// The review status of a card was changed, sent as the `review` event
+    Review {
+        id: CardId,
+        status: ReviewStatus,
+        reviewer: String,
+    },
+}
+
+impl Event {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Card { .. } => "card",
+            Self::Review { .. } => "review",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Events(broadcast::Sender<Event>);
+
+impl Default for Events {
+    fn default() -> Self {
+        Self(broadcast::channel(CAPACITY).0)
+    }
+}
+
+impl Events {
+    /// Sends an event to every connected client
+    pub fn send(&self, event: Event) {
+        // Fails only when no client is connected
+        let _ = self.0.send(event);
+    }
+}
+
+pub async fn subscribe(
+    State(state): State<SharedState>,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    let receiver = state.read().unwrap().events.0.subscribe();
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse = sse::Event::default()
+                        .event(event.name())
+                        .json_data(&event)
+                        .unwrap();
+                    return Some((Ok(sse), receiver));
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)))
+}
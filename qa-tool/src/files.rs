@@ -0,0 +1,144 @@
+//! Serving of the front end files and card images. Requests are only ever resolved inside the
+//! configured directories, so the server cannot be used to read other files on the machine.
+
+use std::{
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+};
+use data::{CardDb, CardId};
+use tokio_util::io::ReaderStream;
+
+use crate::error::ApiError;
+
+/// Resolves a requested relative path inside `root`. Returns `None` if the path is absolute,
+/// leaves `root` through `..` or a symbolic link, or does not exist.
+pub fn resolve(root: &Path, request: &str) -> Option<PathBuf> {
+    let relative = Path::new(request);
+    if request.is_empty()
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let root = root.canonicalize().ok()?;
+    let path = root.join(relative).canonicalize().ok()?;
+    (path.starts_with(&root) && path.is_file()).then_some(path)
+}
+
+/// Finds the image of a card printing, requested by its image name with or without the
+/// extension (`EB01-001_p1.png`, `EB01-001_p1`), or by the card id for its base printing. Only
+/// image names that belong to a card in the database are returned.
+pub fn image_name(db: &CardDb, request: &str) -> Option<String> {
+    let name = request.strip_suffix(".png").unwrap_or(request);
+    let card = db.get(CardId::from_str(name).ok()?)?;
+
+    if name == card.id.to_string() {
+        return Some(card.image_name.clone());
+    }
+
+    card.all_printings()
+        .into_iter()
+        .map(|printing| printing.image_name)
+        .find(|image| image.strip_suffix(".png").unwrap_or(image) == name)
+}
+
+/// Streams a file that was resolved with [`resolve`]
+pub async fn serve(path: &Path) -> Result<Response, ApiError> {
+    let not_found = || ApiError::FileNotFound(path.display().to_string());
+    let file = tokio::fs::File::open(path).await.map_err(|_| not_found())?;
+    let content_type = mime_guess::from_path(path)
+        .first_raw()
+        .unwrap_or("application/octet-stream");
+
+    let body = axum::body::Body::from_stream(ReaderStream::new(file));
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory with an `assets` root inside it and a file outside of the root
+    fn sandbox(name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("qa-tool-{name}-{}", std::process::id()));
+        let root = dir.join("assets");
+        std::fs::create_dir_all(root.join("nested")).unwrap();
+        std::fs::write(root.join("index.html"), "<body></body>").unwrap();
+        std::fs::write(root.join("nested/style.css"), "body {}").unwrap();
+        std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn resolves_files_inside_root() {
+        let (dir, root) = sandbox("inside");
+        assert!(resolve(&root, "index.html").is_some());
+        assert!(resolve(&root, "nested/style.css").is_some());
+        assert!(resolve(&root, "missing.js").is_none());
+        assert!(resolve(&root, "").is_none());
+        assert!(resolve(&root, "nested").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_parent_directories() {
+        let (dir, root) = sandbox("parent");
+        assert!(resolve(&root, "../secret.txt").is_none());
+        assert!(resolve(&root, "nested/../../secret.txt").is_none());
+        assert!(resolve(&root, "nested/../index.html").is_none());
+        assert!(resolve(&root, "./index.html").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        let (dir, root) = sandbox("absolute");
+        let secret = dir.join("secret.txt");
+        assert!(resolve(&root, secret.to_str().unwrap()).is_none());
+        let index = root.join("index.html");
+        assert!(resolve(&root, index.to_str().unwrap()).is_none());
+        assert!(resolve(&root, "/etc/passwd").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_links_leaving_root() {
+        let (dir, root) = sandbox("link");
+        std::os::unix::fs::symlink(dir.join("secret.txt"), root.join("link.txt")).unwrap();
+        assert!(resolve(&root, "link.txt").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn images_are_looked_up_by_known_names() {
+        let db = CardDb::from_jsonl(concat!(
+            r#"{"id":"EB01-001","release_set":"EB01","rarity":"L","ty":"LEADER","name":"Kouzuki Oden","image_name":"EB01-001.png","cost_life":4,"power":5000,"counter":null,"color":["Red","Green"],"effect":null,"trigger":null,"subtype":[],"attribute":[]}"#,
+            "\n",
+            r#"{"id":"EB01-001","release_set":"EB01","rarity":"L","ty":"LEADER","name":"Kouzuki Oden","image_name":"EB01-001_p1.png","cost_life":4,"power":5000,"counter":null,"color":["Red","Green"],"effect":null,"trigger":null,"subtype":[],"attribute":[]}"#,
+        ))
+        .unwrap();
+
+        assert_eq!(image_name(&db, "EB01-001").as_deref(), Some("EB01-001.png"));
+        assert_eq!(
+            image_name(&db, "EB01-001_p1").as_deref(),
+            Some("EB01-001_p1.png")
+        );
+        assert_eq!(
+            image_name(&db, "EB01-001_p1.png").as_deref(),
+            Some("EB01-001_p1.png")
+        );
+        assert_eq!(image_name(&db, "EB01-001_p2"), None);
+        assert_eq!(image_name(&db, "EB01-002"), None);
+        assert_eq!(image_name(&db, "../../etc/passwd"), None);
+        assert_eq!(image_name(&db, "EB01-001/../../secret.txt"), None);
+        assert_eq!(image_name(&db, "/etc/passwd"), None);
+    }
+}
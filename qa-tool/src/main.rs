@@ -1,252 +1,425 @@
 use std::{
-    path::Path,
+    collections::BTreeMap,
     str::FromStr,
     sync::{Arc, RwLock},
 };
 
 use axum::{
-    extract::State,
-    http::{header, StatusCode, Uri},
-    response::{Html, IntoResponse},
+    extract::{rejection::JsonRejection, Query, State},
+    http::{StatusCode, Uri},
+    response::{Html, Response},
     routing::{get, on, post, MethodFilter},
     Json,
 };
-use data::{Attribute, CardData, CardId, Color, Subtype};
-use serde::{Deserialize, Serialize};
+use data::{
+    correction::{CardPatch, Correction, Corrections},
+    CardData, CardDb, CardId, SetId,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use tokio::net::TcpListener;
-use tokio_util::io::ReaderStream;
-
-async fn get_index() -> Html<String> {
-    Html(
-        tokio::fs::read_to_string("assets/index.html")
-            .await
-            .unwrap(),
-    )
+
+mod api;
+mod audit;
+mod config;
+mod error;
+mod events;
+mod files;
+mod review;
+
+use audit::AuditLog;
+use config::{Config, ConfigError, USAGE};
+use error::{ApiError, Conflict, FieldErrors};
+use events::{Event, Events};
+use review::{Progress, ReviewStatus, Reviews};
+
+async fn get_index(State(state): State<SharedState>) -> Result<Html<String>, ApiError> {
+    let path = state.read().unwrap().config.assets.join("index.html");
+    match tokio::fs::read_to_string(&path).await {
+        Ok(html) => Ok(Html(html)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(ApiError::FileNotFound(path.display().to_string()))
+        }
+        Err(e) => Err(ApiError::Read(path, e)),
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+pub struct QaState {
+    /// Cards as the scraper produced them, which corrections are relative to
+    scraped: CardDb,
+    /// Cards with the corrections applied
+    db: CardDb,
+    corrections: Corrections,
+    reviews: Reviews,
+    audit: AuditLog,
+    events: Events,
+    config: Config,
+    /// Held while changes are written to disk. Only one change is saved at a time, so the revision
+    /// checked before writing is still current afterwards, while the state itself stays unlocked
+    /// for readers during the writes.
+    saving: Arc<tokio::sync::Mutex<()>>,
+}
+
+type SharedState = Arc<RwLock<QaState>>;
+
+/// A card along with its review state and revision
+#[derive(Serialize, Debug, Clone)]
 pub struct CardMetadata {
-    pub id: String,
-    pub name: String,
-    pub ty: String,
-    pub subtypes: Vec<String>,
-    pub colors: Vec<String>,
-    pub attributes: Vec<String>,
-    pub cost_life: usize,
-    pub power: Option<usize>,
-    pub counter: Option<usize>,
-    pub effect: Option<String>,
-    pub trigger: Option<String>,
+    #[serde(flatten)]
+    pub card: CardData,
+    pub status: ReviewStatus,
+    /// Has to be sent back when editing the card, see [`AuditLog`]
+    pub revision: u64,
 }
 
-pub async fn get_initial_metadata() -> Json<CardMetadata> {
-    Json(CardMetadata {
-        id: "EB01-002".to_string(),
-        name: "Izo".to_string(),
-        ty: "Leader".to_string(),
-        subtypes: vec!["Land of Wano".to_string(), "Whitebeard Pirates".to_string()],
-        colors: vec!["Red".to_string()],
-        attributes: vec!["Ranged".to_string()],
-        cost_life: 5,
-        power: Some(7000),
-        counter: None,
-        effect: Some(
-            "[On Play] Give up to 1 rested Don!! card to your Leader or 1 of your Characters."
-                .to_string(),
-        ),
-        trigger: None,
-    })
+impl CardMetadata {
+    fn new(state: &QaState, card: &CardData) -> Self {
+        Self {
+            card: card.clone(),
+            status: state.reviews.status(card.id),
+            revision: state.audit.revision(card.id),
+        }
+    }
 }
 
-pub async fn submit_metadata(
-    State(db): State<Arc<RwLock<Vec<CardData>>>>,
-    Json(meta): Json<CardMetadata>,
-) {
-    let id = CardId::from_str(&meta.id).unwrap();
-    let mut db = db.write().unwrap();
-    let card = db.iter_mut().find(|card| card.id == id).unwrap();
-
-    card.color = meta
-        .colors
-        .into_iter()
-        .map(|c| Color::from_str(&c).unwrap())
-        .collect();
-    card.attribute = meta
-        .attributes
-        .into_iter()
-        .map(|a| Attribute::from_str(&a).unwrap())
-        .collect();
-    card.subtype = meta
-        .subtypes
-        .into_iter()
-        .map(|s| Subtype::from_str(&s).unwrap())
-        .collect();
-    card.trigger = meta.trigger;
-    card.effect = meta.effect;
-    card.power = meta.power;
-    card.counter = meta.counter;
-    card.cost_life = meta.cost_life;
+/// Identifies the reviewer making a request
+#[derive(Deserialize)]
+pub struct ReviewerParams {
+    pub reviewer: Option<String>,
 }
 
-pub async fn next_meta(
-    axum::extract::Path(current): axum::extract::Path<String>,
-    State(db): State<Arc<RwLock<Vec<CardData>>>>,
-) -> Json<CardMetadata> {
-    let id = CardId::from_str(&current).unwrap();
-    let db = db.read().unwrap();
-    let pos = db.iter().position(|card| card.id == id).unwrap();
-    let next = &db[pos + 1 % db.len()];
-    Json(CardMetadata {
-        id: next.id.to_string(),
-        name: next.name.clone(),
-        ty: format!("{:?}", next.ty),
-        subtypes: next
-            .subtype
-            .iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>(),
-        colors: next
-            .color
-            .iter()
-            .map(|s| format!("{s:?}"))
-            .collect::<Vec<_>>(),
-        attributes: next
-            .attribute
-            .iter()
-            .map(|s| format!("{s:?}"))
-            .collect::<Vec<_>>(),
-        cost_life: next.cost_life,
-        power: next.power,
-        counter: next.counter,
-        effect: next.effect.clone(),
-        trigger: next.trigger.clone(),
-    })
+/// Identifies the reviewer and the revision of the card an edit is based on
+#[derive(Deserialize)]
+pub struct EditParams {
+    pub reviewer: Option<String>,
+    /// Revision of the card the edit is based on
+    pub revision: Option<u64>,
 }
 
-pub async fn prev_meta(
-    axum::extract::Path(current): axum::extract::Path<String>,
-    State(db): State<Arc<RwLock<Vec<CardData>>>>,
-) -> Json<CardMetadata> {
-    let id = CardId::from_str(&current).unwrap();
-    let db = db.read().unwrap();
-    let pos = db.iter().position(|card| card.id == id).unwrap();
-    let next = &db[pos.wrapping_sub(1) % db.len()];
-    Json(CardMetadata {
-        id: next.id.to_string(),
-        name: next.name.clone(),
-        ty: format!("{:?}", next.ty),
-        subtypes: next
-            .subtype
-            .iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>(),
-        colors: next
-            .color
-            .iter()
-            .map(|s| format!("{s:?}"))
-            .collect::<Vec<_>>(),
-        attributes: next
-            .attribute
-            .iter()
-            .map(|s| format!("{s:?}"))
-            .collect::<Vec<_>>(),
-        cost_life: next.cost_life,
-        power: next.power,
-        counter: next.counter,
-        effect: next.effect.clone(),
-        trigger: next.trigger.clone(),
+fn parse_card_id(id: &str) -> Result<CardId, ApiError> {
+    CardId::from_str(id).map_err(|source| ApiError::InvalidCardId {
+        id: id.to_string(),
+        source,
     })
 }
 
-pub async fn get_image(axum::extract::Path(id): axum::extract::Path<String>) -> impl IntoResponse {
-    let path = Path::new("../scraper/cache/images").join(format!("{id}.png"));
-    let filename = match path.file_name() {
-        Some(name) => name,
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                "File name couldn't be determined".to_string(),
-            ))
-        }
-    };
+/// Deserializes a JSON request body. The body is read as a plain JSON value first, so that a
+/// value of the wrong type is reported with the path of its field, see [`ApiError::InvalidData`].
+fn parse_body<T: DeserializeOwned>(
+    body: Result<Json<serde_json::Value>, JsonRejection>,
+) -> Result<T, ApiError> {
+    let Json(value) = body?;
+    Ok(serde_path_to_error::deserialize(value)?)
+}
+
+/// Starts at the reviewer's resume point, or at the first unreviewed card for new reviewers
+pub async fn get_initial_metadata(
+    State(state): State<SharedState>,
+    Query(params): Query<ReviewerParams>,
+) -> Result<Json<CardMetadata>, ApiError> {
+    let state = state.read().unwrap();
+    let db = &state.db;
+    let card = params
+        .reviewer
+        .and_then(|reviewer| state.reviews.resume_point(&reviewer))
+        .and_then(|id| db.get(id))
+        .or_else(|| state.reviews.next_unreviewed(db, None, None))
+        .or_else(|| db.get_index(0))
+        .ok_or(ApiError::NoUnreviewedCards)?;
+
+    Ok(Json(CardMetadata::new(&state, card)))
+}
+
+/// Runs blocking file I/O without holding up the async worker threads
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(f).await.unwrap()
+}
 
-    let file = match tokio::fs::File::open(&path).await {
-        Ok(file) => file,
-        Err(err) => return Err((StatusCode::NOT_FOUND, format!("File not found: {}", err))),
+/// Applies `edit` to a card and records the result as a correction over the scraped data, then
+/// updates the card's review status: saving the scraped data unchanged confirms it is correct.
+///
+/// The edit must be based on the card's current revision, otherwise someone else changed the
+/// card in the meantime and the edit is rejected instead of overwriting their changes. The files
+/// are written without holding the state lock, see [`QaState::saving`].
+async fn save_card(
+    shared: &SharedState,
+    id: CardId,
+    params: &EditParams,
+    edit: impl FnOnce(&mut CardData),
+) -> Result<CardMetadata, ApiError> {
+    let reviewer = params.reviewer.as_deref().unwrap_or("unknown");
+    let (saving, corrections_path, audit_path) = {
+        let state = shared.read().unwrap();
+        (
+            state.saving.clone(),
+            state.config.corrections_path(),
+            state.audit.path().to_path_buf(),
+        )
     };
-    let content_type = match mime_guess::from_path(&path).first_raw() {
-        Some(mime) => mime,
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                "MIME Type couldn't be determined".to_string(),
-            ))
+    let _saving = saving.lock().await;
+
+    let (card, status, previous, corrections, entry) = {
+        let state = shared.read().unwrap();
+        let before = state.db.get(id).cloned().ok_or(ApiError::UnknownCard(id))?;
+        let mut card = before.clone();
+        edit(&mut card);
+
+        let mut errors = FieldErrors::new();
+        if card.color.is_empty() {
+            errors.insert(
+                "color".to_string(),
+                "Cards need at least one color".to_string(),
+            );
         }
+        if params.revision.is_none() {
+            errors.insert(
+                "revision".to_string(),
+                "The revision of the card being edited is required".to_string(),
+            );
+        }
+        if !errors.is_empty() {
+            return Err(ApiError::InvalidFields(errors));
+        }
+
+        let revision = params.revision.unwrap_or_default();
+        if revision != state.audit.revision(id) {
+            return Err(ApiError::Conflict(Box::new(Conflict {
+                current: CardMetadata::new(&state, &before),
+                changes: state.audit.changes_since(id, revision),
+            })));
+        }
+
+        let scraped = state.scraped.get(id).ok_or(ApiError::UnknownCard(id))?;
+        let changes = CardPatch::diff(scraped, &card);
+        let status = if changes.is_empty() {
+            ReviewStatus::Verified
+        } else {
+            ReviewStatus::Fixed
+        };
+
+        let mut corrections = state.corrections.clone();
+        corrections.set(Correction::new(id, reviewer, changes));
+        let entry = state.audit.entry(reviewer, &before, &card);
+        (card, status, state.corrections.clone(), corrections, entry)
     };
 
-    let stream = ReaderStream::new(file);
-    let body = axum::body::Body::from_stream(stream);
+    let (corrections, entry) = blocking(move || -> Result<_, ApiError> {
+        corrections.save(&corrections_path)?;
 
-    let headers = [
-        (header::CONTENT_TYPE, content_type.to_string()),
-        (
-            header::CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{:?}\"", filename),
-        ),
-    ];
-    Ok((headers, body))
-}
+        // Without an audit entry the revision doesn't change, so the corrections on disk have to
+        // go back to what the revision describes
+        if let Some(entry) = &entry {
+            if let Err(e) = AuditLog::append(&audit_path, entry) {
+                if let Err(restore) = previous.save(&corrections_path) {
+                    eprintln!("Failed to restore the corrections after a failed audit: {restore}");
+                }
+                return Err(e.into());
+            }
+        }
+        Ok((corrections, entry))
+    })
+    .await?;
 
-pub async fn get_file(uri: Uri) -> impl IntoResponse {
-    let path = uri.path().strip_prefix("/").unwrap();
-    let filename = match Path::new(path).file_name() {
-        Some(name) => name,
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                "File name couldn't be determined".to_string(),
-            ))
+    let (reviews, reviews_path, metadata) = {
+        let mut state = shared.write().unwrap();
+        state.corrections = corrections;
+        if let Some(entry) = entry {
+            state.audit.push(entry);
         }
-    };
+        state.db.insert(card);
+        state.reviews.set_status(id, status, reviewer);
 
-    let file = match tokio::fs::File::open(path).await {
-        Ok(file) => file,
-        Err(err) => return Err((StatusCode::NOT_FOUND, format!("File not found: {}", err))),
+        let metadata = CardMetadata::new(&state, state.db.get(id).unwrap());
+        state.events.send(Event::Card {
+            card: Box::new(metadata.clone()),
+            reviewer: reviewer.to_string(),
+        });
+        (state.reviews.clone(), state.config.reviews_path(), metadata)
     };
-    let content_type = match mime_guess::from_path(&path).first_raw() {
-        Some(mime) => mime,
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                "MIME Type couldn't be determined".to_string(),
-            ))
+    blocking(move || reviews.save(reviews_path)).await?;
+    Ok(metadata)
+}
+
+/// Saves the editable fields of the submitted card, see [`CardPatch`]. The other fields, like
+/// the image and printings, always come from the scraped data.
+pub async fn submit_metadata(
+    State(state): State<SharedState>,
+    Query(params): Query<EditParams>,
+    submitted: Result<Json<serde_json::Value>, JsonRejection>,
+) -> Result<Json<CardMetadata>, ApiError> {
+    let submitted: CardData = parse_body(submitted)?;
+    let card = save_card(&state, submitted.id, &params, |card| {
+        CardPatch::diff(card, &submitted).apply(card)
+    })
+    .await?;
+    Ok(Json(card))
+}
+
+#[derive(Deserialize)]
+pub struct ReviewUpdate {
+    pub status: ReviewStatus,
+    pub reviewer: String,
+}
+
+pub async fn set_review_status(
+    axum::extract::Path(id): axum::extract::Path<String>,
+    State(state): State<SharedState>,
+    update: Result<Json<serde_json::Value>, JsonRejection>,
+) -> Result<StatusCode, ApiError> {
+    let update: ReviewUpdate = parse_body(update)?;
+    let id = parse_card_id(&id)?;
+    // The reviews file is rewritten in full, so it has to be saved in order, see QaState::saving
+    let saving = state.read().unwrap().saving.clone();
+    let _saving = saving.lock().await;
+
+    let (reviews, path) = {
+        let mut state = state.write().unwrap();
+        if !state.db.contains(id) {
+            return Err(ApiError::UnknownCard(id));
         }
+
+        state
+            .reviews
+            .set_status(id, update.status, &update.reviewer);
+        state.events.send(Event::Review {
+            id,
+            status: update.status,
+            reviewer: update.reviewer,
+        });
+        (state.reviews.clone(), state.config.reviews_path())
     };
+    blocking(move || reviews.save(path)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct NextUnreviewedParams {
+    /// Only look for cards in this set, so reviewers can split the work by set
+    pub set: Option<String>,
+}
+
+pub async fn next_unreviewed(
+    axum::extract::Path(current): axum::extract::Path<String>,
+    State(state): State<SharedState>,
+    Query(params): Query<NextUnreviewedParams>,
+) -> Result<Json<CardMetadata>, ApiError> {
+    let id = parse_card_id(&current)?;
+    let set = params
+        .set
+        .filter(|set| !set.is_empty())
+        .map(|set| SetId::from_str(&set).map_err(|source| ApiError::InvalidSet { set, source }))
+        .transpose()?;
 
-    let stream = ReaderStream::new(file);
-    let body = axum::body::Body::from_stream(stream);
+    let state = state.read().unwrap();
+    let card = state
+        .reviews
+        .next_unreviewed(&state.db, Some(id), set)
+        .ok_or(ApiError::NoUnreviewedCards)?;
+
+    Ok(Json(CardMetadata::new(&state, card)))
+}
+
+pub async fn get_progress(State(state): State<SharedState>) -> Json<BTreeMap<SetId, Progress>> {
+    let state = state.read().unwrap();
+    Json(state.reviews.progress(&state.db))
+}
+
+/// The card `offset` positions away from `current` in id order, wrapping around
+fn neighbour(state: &QaState, current: &str, offset: isize) -> Result<CardMetadata, ApiError> {
+    let id = parse_card_id(current)?;
+    let db = &state.db;
+    let pos = db.position(id).ok_or(ApiError::UnknownCard(id))?;
+    let index = (pos as isize + offset).rem_euclid(db.len() as isize) as usize;
+    let card = db.get_index(index).unwrap();
+    Ok(CardMetadata::new(state, card))
+}
+
+pub async fn next_meta(
+    axum::extract::Path(current): axum::extract::Path<String>,
+    State(state): State<SharedState>,
+) -> Result<Json<CardMetadata>, ApiError> {
+    neighbour(&state.read().unwrap(), &current, 1).map(Json)
+}
 
-    let headers = [
-        (header::CONTENT_TYPE, content_type.to_string()),
+pub async fn prev_meta(
+    axum::extract::Path(current): axum::extract::Path<String>,
+    State(state): State<SharedState>,
+) -> Result<Json<CardMetadata>, ApiError> {
+    neighbour(&state.read().unwrap(), &current, -1).map(Json)
+}
+
+/// Serves the image of a card printing, see [`files::image_name`]
+pub async fn get_image(
+    axum::extract::Path(name): axum::extract::Path<String>,
+    State(state): State<SharedState>,
+) -> Result<Response, ApiError> {
+    let (root, image) = {
+        let state = state.read().unwrap();
         (
-            header::CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{:?}\"", filename),
-        ),
-    ];
-    Ok((headers, body))
+            state.config.images.clone(),
+            files::image_name(&state.db, &name),
+        )
+    };
+
+    let path = image
+        .and_then(|image| files::resolve(&root, &image))
+        .ok_or(ApiError::FileNotFound(name))?;
+    files::serve(&path).await
+}
+
+/// Serves the front end files from the assets directory
+pub async fn get_file(uri: Uri, State(state): State<SharedState>) -> Result<Response, ApiError> {
+    let request = uri.path().trim_start_matches('/');
+    // The page refers to the front end files as `assets/...`
+    let request = request.strip_prefix("assets/").unwrap_or(request);
+    let root = state.read().unwrap().config.assets.clone();
+
+    let path = files::resolve(&root, request)
+        .ok_or_else(|| ApiError::FileNotFound(uri.path().to_string()))?;
+    files::serve(&path).await
+}
+
+/// Unwraps the result of loading one of the files the tool works on, exiting with the error
+/// instead of panicking when the file cannot be read
+fn or_exit<T>(result: Result<T, impl std::fmt::Display>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    })
 }
 
 #[tokio::main]
 async fn main() {
     env_logger::init();
 
-    let file = tokio::fs::read_to_string("../scraper/cache/card_db_2.jsonl")
-        .await
-        .unwrap();
-    let card_db: Vec<CardData> = serde_json::Deserializer::from_str(&file)
-        .into_iter::<CardData>()
-        .map(|card| card.unwrap())
-        .collect();
+    let config = match Config::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(ConfigError::Help) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    let scraped = or_exit(CardDb::load(&config.db));
+    let corrections = or_exit(Corrections::load(config.corrections_path()));
+    let mut db = scraped.clone();
+    for id in corrections.apply(&mut db) {
+        eprintln!("Correction for unknown card {id} was not applied");
+    }
+
+    let state = QaState {
+        scraped,
+        db,
+        corrections,
+        reviews: or_exit(Reviews::load(config.reviews_path())),
+        audit: or_exit(AuditLog::load(config.audit_path())),
+        events: Events::default(),
+        config: config.clone(),
+        saving: Arc::default(),
+    };
 
     let router = axum::Router::new()
         .route("/", get(get_index))
@@ -254,11 +427,27 @@ async fn main() {
         .route("/submit", post(submit_metadata))
         .route("/next/:current", get(next_meta))
         .route("/prev/:current", get(prev_meta))
+        .route("/next_unreviewed/:current", get(next_unreviewed))
+        .route("/review/:id", post(set_review_status))
+        .route("/progress", get(get_progress))
+        .route("/events", get(events::subscribe))
+        .route("/cards", get(api::list_cards))
+        .route("/cards/:id", get(api::get_card).patch(api::patch_card))
+        .route("/sets", get(api::list_sets))
+        .route("/sets/:set_id/cards", get(api::list_set_cards))
+        .route("/subtypes", get(api::list_subtypes))
+        .route("/vocabulary", get(api::get_vocabulary))
         .route("/images/:id", get(get_image))
         .fallback(on(MethodFilter::GET, get_file))
-        .with_state(Arc::new(RwLock::new(card_db)));
+        .with_state(Arc::new(RwLock::new(state)));
 
-    axum::serve(TcpListener::bind("0.0.0.0:8080").await.unwrap(), router)
+    println!(
+        "Reviewing the {} database {} on http://{}",
+        config.region,
+        config.db.display(),
+        config.listen
+    );
+    axum::serve(TcpListener::bind(config.listen).await.unwrap(), router)
         .await
         .unwrap();
 }
@@ -0,0 +1,157 @@
+//! Review progress of the QA team, saved next to the corrections so reviewers can split the
+//! database between them and pick up where they left off.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use data::{CardData, CardDb, CardId, SetId};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ReviewError {
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+
+    #[error("Failed to write {0}: {1}")]
+    Write(PathBuf, #[source] std::io::Error),
+
+    #[error("Invalid review file {0}: {1}")]
+    Parse(PathBuf, #[source] serde_json::Error),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReviewStatus {
+    #[default]
+    Unreviewed,
+    /// The scraped data matches the card
+    Verified,
+    /// Something is wrong with the card but it was not corrected yet
+    NeedsFix,
+    /// The card was corrected
+    Fixed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Review {
+    pub status: ReviewStatus,
+    pub reviewer: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// Number of cards in each review status
+#[derive(Debug, Copy, Clone, Default, Serialize)]
+pub struct Progress {
+    pub total: usize,
+    pub unreviewed: usize,
+    pub verified: usize,
+    pub needs_fix: usize,
+    pub fixed: usize,
+}
+
+impl Progress {
+    fn add(&mut self, status: ReviewStatus) {
+        self.total += 1;
+        match status {
+            ReviewStatus::Unreviewed => self.unreviewed += 1,
+            ReviewStatus::Verified => self.verified += 1,
+            ReviewStatus::NeedsFix => self.needs_fix += 1,
+            ReviewStatus::Fixed => self.fixed += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Reviews {
+    /// Cards without an entry are unreviewed
+    #[serde(default)]
+    cards: BTreeMap<CardId, Review>,
+    /// The last card each reviewer worked on
+    #[serde(default)]
+    resume: BTreeMap<String, CardId>,
+}
+
+impl Reviews {
+    /// Loads the review file. A missing file means the review has not started yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReviewError> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(json) => {
+                serde_json::from_str(&json).map_err(|e| ReviewError::Parse(path.to_path_buf(), e))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(ReviewError::Io(path.to_path_buf(), e)),
+        }
+    }
+
+    /// Writes the review file, replacing it atomically
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReviewError> {
+        let path = path.as_ref();
+        let temp = path.with_extension("json.tmp");
+        let json = serde_json::to_string_pretty(self).unwrap();
+        std::fs::write(&temp, json).map_err(|e| ReviewError::Write(temp.clone(), e))?;
+        std::fs::rename(&temp, path).map_err(|e| ReviewError::Write(path.to_path_buf(), e))
+    }
+
+    pub fn status(&self, id: CardId) -> ReviewStatus {
+        self.cards
+            .get(&id)
+            .map_or(ReviewStatus::Unreviewed, |review| review.status)
+    }
+
+    /// Records the status of a card and makes it the reviewer's resume point
+    pub fn set_status(&mut self, id: CardId, status: ReviewStatus, reviewer: &str) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+
+        self.cards.insert(
+            id,
+            Review {
+                status,
+                reviewer: reviewer.to_string(),
+                timestamp,
+            },
+        );
+        self.resume.insert(reviewer.to_string(), id);
+    }
+
+    pub fn resume_point(&self, reviewer: &str) -> Option<CardId> {
+        self.resume.get(reviewer).copied()
+    }
+
+    /// The first unreviewed card after `after` in id order, wrapping around to the start of the
+    /// database. Only cards from `set` are considered if it is given.
+    pub fn next_unreviewed<'a>(
+        &self,
+        db: &'a CardDb,
+        after: Option<CardId>,
+        set: Option<SetId>,
+    ) -> Option<&'a CardData> {
+        let start = after
+            .and_then(|id| db.position(id))
+            .map_or(0, |position| position + 1);
+
+        (0..db.len())
+            .filter_map(|offset| db.get_index((start + offset) % db.len()))
+            .filter(|card| set.is_none() || set == Some(card.id.set))
+            .find(|card| self.status(card.id) == ReviewStatus::Unreviewed)
+    }
+
+    /// Review progress of every set in the database
+    pub fn progress(&self, db: &CardDb) -> BTreeMap<SetId, Progress> {
+        let mut progress: BTreeMap<SetId, Progress> = BTreeMap::new();
+        for card in db.iter() {
+            progress
+                .entry(card.id.set)
+                .or_default()
+                .add(self.status(card.id));
+        }
+        progress
+    }
+}
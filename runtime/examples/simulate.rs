@@ -0,0 +1,81 @@
+//! Plays random games between randomly built decks and reports how they ended.
+//!
+//! Usage: `cargo run -p runtime --no-default-features --example simulate [games] [card_db.jsonl]`
+
+use std::sync::Arc;
+
+use data::{CardData, CardDb, CardType};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use runtime::game::{GameState, PlayerId, PlayerSetup};
+
+const MAX_ACTIONS: usize = 10_000;
+
+fn random_setup(cards: &[Arc<CardData>], rng: &mut StdRng) -> PlayerSetup {
+    let leaders: Vec<_> = cards
+        .iter()
+        .filter(|card| card.ty == CardType::Leader)
+        .collect();
+    let leader = (*leaders.choose(rng).unwrap()).clone();
+
+    let mut pool: Vec<_> = cards
+        .iter()
+        .filter(|card| {
+            card.ty != CardType::Leader && card.color.iter().any(|c| leader.color.contains(c))
+        })
+        .collect();
+    pool.shuffle(rng);
+
+    let deck = pool
+        .into_iter()
+        .flat_map(|card| (0..4).map(|_| card.clone()))
+        .take(50)
+        .collect();
+
+    PlayerSetup { leader, deck }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let games = args.next().map_or(1000, |n| n.parse::<u64>().unwrap());
+    let path = args
+        .next()
+        .unwrap_or_else(|| "cache/en/card_db.jsonl".to_string());
+
+    let card_db = CardDb::load(path).unwrap();
+    let cards: Vec<Arc<CardData>> = card_db.iter().cloned().map(Arc::new).collect();
+
+    let mut wins = [0usize; 2];
+    let mut unfinished = 0;
+    let mut turns = 0;
+
+    for seed in 0..games {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let setups = [
+            random_setup(&cards, &mut rng),
+            random_setup(&cards, &mut rng),
+        ];
+        let mut game = GameState::new(setups, seed).unwrap();
+
+        for _ in 0..MAX_ACTIONS {
+            let actions = game.legal_actions();
+            let Some(action) = actions.choose(&mut rng) else {
+                break;
+            };
+            game.apply(*action).unwrap();
+        }
+
+        turns += game.turn;
+        match game.winner {
+            Some(PlayerId::First) => wins[0] += 1,
+            Some(PlayerId::Second) => wins[1] += 1,
+            None => unfinished += 1,
+        }
+    }
+
+    println!(
+        "{games} games: first player won {}, second player won {}, {unfinished} unfinished, {:.1} turns on average",
+        wins[0],
+        wins[1],
+        turns as f64 / games as f64
+    );
+}
@@ -0,0 +1,18 @@
+-- Monkey.D.Luffy (ST01-001)
+-- [Activate: Main] [Once Per Turn] Give this Leader or 1 of your Characters up to 1 rested DON!! card.
+
+register("activate_main", function(game)
+    if not game:once_per_turn() then
+        return
+    end
+
+    -- Prefer the strongest Character, falling back to the Leader
+    local target = game:leader("you")
+    for _, character in ipairs(game:characters("you")) do
+        if character.power > target.power then
+            target = character
+        end
+    end
+
+    game:give_don(target.id, 1)
+end)
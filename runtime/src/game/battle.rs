@@ -0,0 +1,301 @@
+use std::str::FromStr;
+
+use data::{
+    effect::{Action as EffectAction, Effect, Keyword, Player, Target, Timing},
+    CardType,
+};
+
+use super::{
+    Action, Expiry, FieldCard, GameEvent, GameState, InstanceId, PlayerId, PowerModifier, RuleError,
+};
+
+/// The steps of a battle that wait on a decision from the defending player. The Attack step is
+/// resolved when the attack is declared and the Damage step when the Counter step ends.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BattleStep {
+    Block,
+    Counter,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Battle {
+    pub attacker: InstanceId,
+    /// The card being attacked. Changes to the blocker if one is declared.
+    pub target: InstanceId,
+    pub step: BattleStep,
+}
+
+impl GameState {
+    pub(super) fn can_attack(&self, card: &FieldCard) -> Result<(), RuleError> {
+        // Neither player may attack during their own first turn
+        if self.turn <= 2 {
+            return Err(RuleError::FirstTurnAttack);
+        }
+
+        if card.rested
+            || (card.played_this_turn && !card.card.has_keyword(Keyword::Rush))
+            || card.card.ty() == CardType::Stage
+        {
+            return Err(RuleError::CannotAttack(card.instance()));
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn attack(
+        &mut self,
+        attacker: InstanceId,
+        target: InstanceId,
+        events: &mut Vec<GameEvent>,
+    ) -> Result<(), RuleError> {
+        self.require_main()?;
+        let player = self.active;
+
+        let card = self
+            .player(player)
+            .battlers()
+            .find(|card| card.instance() == attacker)
+            .ok_or(RuleError::NotOnField(attacker))?;
+        self.can_attack(card)?;
+
+        let opponent = self.player(player.opponent());
+        let valid_target = opponent.leader.instance() == target
+            || opponent
+                .characters
+                .iter()
+                .any(|card| card.instance() == target && card.rested);
+        if !valid_target {
+            return Err(RuleError::InvalidTarget(target));
+        }
+
+        self.player_mut(player)
+            .field_card_mut(attacker)
+            .unwrap()
+            .rested = true;
+        self.battle = Some(Battle {
+            attacker,
+            target,
+            step: BattleStep::Block,
+        });
+
+        events.push(GameEvent::AttackDeclared { attacker, target });
+        Ok(())
+    }
+
+    fn require_step(&self, step: BattleStep) -> Result<Battle, RuleError> {
+        match self.battle {
+            Some(battle) if battle.step == step => Ok(battle),
+            Some(_) => Err(RuleError::WrongBattleStep(step)),
+            None => Err(RuleError::NotInBattle),
+        }
+    }
+
+    pub(super) fn block(
+        &mut self,
+        blocker: Option<InstanceId>,
+        events: &mut Vec<GameEvent>,
+    ) -> Result<(), RuleError> {
+        let mut battle = self.require_step(BattleStep::Block)?;
+        let defender = self.active.opponent();
+
+        if let Some(blocker) = blocker {
+            let card = self
+                .player_mut(defender)
+                .characters
+                .iter_mut()
+                .find(|card| card.instance() == blocker)
+                .ok_or(RuleError::NotOnField(blocker))?;
+
+            if card.rested || !card.card.has_keyword(Keyword::Blocker) {
+                return Err(RuleError::CannotBlock(blocker));
+            }
+
+            card.rested = true;
+            battle.target = blocker;
+            events.push(GameEvent::Blocked { blocker });
+        }
+
+        battle.step = BattleStep::Counter;
+        self.battle = Some(battle);
+        Ok(())
+    }
+
+    pub(super) fn counter(
+        &mut self,
+        instance: InstanceId,
+        events: &mut Vec<GameEvent>,
+    ) -> Result<(), RuleError> {
+        let battle = self.require_step(BattleStep::Counter)?;
+        let defender = self.active.opponent();
+        let state = self.player(defender);
+        // The target may have been removed by an effect, leaving nothing to counter for
+        if state.field_card(battle.target).is_none() {
+            return Err(RuleError::InvalidTarget(battle.target));
+        }
+        let position = state
+            .hand_position(instance)
+            .ok_or(RuleError::NotInHand(instance))?;
+        let card = &state.hand[position];
+
+        let power = match card.ty() {
+            CardType::Character if card.counter() > 0 => card.counter() as isize,
+            CardType::Event => {
+                let power = counter_event_power(card).ok_or(RuleError::CannotCounter(instance))?;
+                self.pay(defender, card.cost())?;
+                power
+            }
+            _ => return Err(RuleError::CannotCounter(instance)),
+        };
+
+        let state = self.player_mut(defender);
+        let card = state.hand.remove(position);
+        state.trash.push(card);
+        if let Some(target) = state.field_card_mut(battle.target) {
+            target.power_modifiers.push(PowerModifier {
+                amount: power,
+                expires: Expiry::EndOfBattle,
+            });
+        }
+
+        events.push(GameEvent::CounterUsed {
+            player: defender,
+            card: instance,
+            power,
+        });
+        Ok(())
+    }
+
+    pub(super) fn resolve_damage(&mut self, events: &mut Vec<GameEvent>) -> Result<(), RuleError> {
+        let battle = self.require_step(BattleStep::Counter)?;
+        let attacker_owner = self.active;
+        let defender = attacker_owner.opponent();
+
+        // When an effect removed the attacker or the target during the battle, the battle ends
+        // without dealing damage
+        let hit = match (
+            self.player(attacker_owner).field_card(battle.attacker),
+            self.player(defender).field_card(battle.target),
+        ) {
+            (Some(attacker), Some(target)) if attacker.power(true) >= target.power(false) => {
+                let damage = if attacker.card.has_keyword(Keyword::DoubleAttack) {
+                    2
+                } else {
+                    1
+                };
+                Some((damage, attacker.card.has_keyword(Keyword::Banish)))
+            }
+            _ => None,
+        };
+
+        if let Some((damage, banish)) = hit {
+            if battle.target == self.player(defender).leader.instance() {
+                self.deal_damage(defender, damage, banish, events);
+            } else {
+                self.player_mut(defender).trash_character(battle.target);
+                events.push(GameEvent::KnockedOut {
+                    player: defender,
+                    card: battle.target,
+                });
+            }
+        }
+
+        for player in self.players.iter_mut() {
+            for card in player.battlers_mut() {
+                card.expire_modifiers(Expiry::EndOfBattle);
+            }
+        }
+
+        self.battle = None;
+        events.push(GameEvent::BattleEnded);
+        Ok(())
+    }
+
+    /// Moves Life cards to hand (or to the trash for [Banish]). Taking damage with no Life cards
+    /// left loses the game.
+    pub fn deal_damage(
+        &mut self,
+        player: PlayerId,
+        amount: usize,
+        banish: bool,
+        events: &mut Vec<GameEvent>,
+    ) {
+        for _ in 0..amount {
+            let state = self.player_mut(player);
+            let Some(card) = state.life.pop() else {
+                self.lose(player, events);
+                return;
+            };
+
+            let instance = card.instance;
+            let trigger = !banish && card.data.trigger.is_some();
+            if banish {
+                state.trash.push(card);
+            } else {
+                state.hand.push(card);
+            }
+
+            events.push(GameEvent::LifeLost {
+                player,
+                card: instance,
+                trigger,
+            });
+        }
+    }
+
+    pub(super) fn legal_battle_actions(&self, battle: &Battle) -> Vec<Action> {
+        let defender = self.player(self.active.opponent());
+        match battle.step {
+            BattleStep::Block => std::iter::once(Action::SkipBlock)
+                .chain(
+                    defender
+                        .characters
+                        .iter()
+                        .filter(|card| {
+                            !card.rested
+                                && card.card.has_keyword(Keyword::Blocker)
+                                && card.instance() != battle.target
+                        })
+                        .map(|card| Action::Block(card.instance())),
+                )
+                .collect(),
+            BattleStep::Counter if defender.field_card(battle.target).is_none() => {
+                vec![Action::EndCounter]
+            }
+            BattleStep::Counter => std::iter::once(Action::EndCounter)
+                .chain(
+                    defender
+                        .hand
+                        .iter()
+                        .filter(|card| match card.ty() {
+                            CardType::Character => card.counter() > 0,
+                            CardType::Event => {
+                                card.cost() <= defender.don.active
+                                    && counter_event_power(card).is_some()
+                            }
+                            _ => false,
+                        })
+                        .map(|card| Action::Counter(card.instance)),
+                )
+                .collect(),
+        }
+    }
+}
+
+/// Power granted by a [Counter] Event whose effect is a plain power boost to one of the
+/// defender's own cards, which covers most counter Events. Other [Counter] Events need a script.
+fn counter_event_power(card: &super::Card) -> Option<isize> {
+    let effect = Effect::from_str(card.data.effect.as_deref()?).ok()?;
+    let ability = effect.abilities_with_timing(Timing::Counter).next()?;
+    let clause = ability.body.first()?;
+
+    match clause.actions.first()? {
+        EffectAction::GivePower {
+            target: Target::Cards {
+                owner: Player::You, ..
+            },
+            amount,
+            ..
+        } if clause.condition.is_none() => Some(*amount),
+        _ => None,
+    }
+}
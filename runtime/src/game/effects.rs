@@ -0,0 +1,147 @@
+//! Primitive state changes used to resolve card effects, whether they come from parsed effect
+//! text or from card scripts. Unlike [`GameState::apply`] these do not check whose turn it is or
+//! which phase the game is in; that is the responsibility of the effect being resolved.
+
+use super::{Expiry, GameEvent, GameState, InstanceId, PlayerId, PowerModifier, RuleError};
+
+impl GameState {
+    pub fn draw_cards(&mut self, player: PlayerId, count: usize, events: &mut Vec<GameEvent>) {
+        for _ in 0..count {
+            match self.player_mut(player).draw() {
+                Some(card) => events.push(GameEvent::CardDrawn { player, card }),
+                None => return self.lose(player, events),
+            }
+        }
+    }
+
+    /// K.O.s a Character, moving it to its owner's trash
+    pub fn ko(
+        &mut self,
+        instance: InstanceId,
+        events: &mut Vec<GameEvent>,
+    ) -> Result<(), RuleError> {
+        let player = self
+            .owner_of(instance)
+            .ok_or(RuleError::NotOnField(instance))?;
+        self.player_mut(player)
+            .trash_character(instance)
+            .ok_or(RuleError::NotOnField(instance))?;
+
+        events.push(GameEvent::KnockedOut {
+            player,
+            card: instance,
+        });
+        Ok(())
+    }
+
+    pub fn set_rested(&mut self, instance: InstanceId, rested: bool) -> Result<(), RuleError> {
+        let player = self
+            .owner_of(instance)
+            .ok_or(RuleError::NotOnField(instance))?;
+        self.player_mut(player)
+            .field_card_mut(instance)
+            .unwrap()
+            .rested = rested;
+        Ok(())
+    }
+
+    pub fn give_power(
+        &mut self,
+        instance: InstanceId,
+        amount: isize,
+        expires: Expiry,
+    ) -> Result<(), RuleError> {
+        let player = self
+            .owner_of(instance)
+            .ok_or(RuleError::NotOnField(instance))?;
+        self.player_mut(player)
+            .field_card_mut(instance)
+            .unwrap()
+            .power_modifiers
+            .push(PowerModifier { amount, expires });
+        Ok(())
+    }
+
+    /// Moves up to `count` DON!! cards from the DON!! deck to the cost area
+    pub fn add_don(
+        &mut self,
+        player: PlayerId,
+        count: usize,
+        rested: bool,
+        events: &mut Vec<GameEvent>,
+    ) {
+        let don = &mut self.player_mut(player).don;
+        let count = count.min(don.deck);
+        don.deck -= count;
+        if rested {
+            don.rested += count;
+        } else {
+            don.active += count;
+        }
+
+        if count > 0 {
+            events.push(GameEvent::DonAdded { player, count });
+        }
+    }
+
+    /// Attaches up to `count` rested DON!! cards from the cost area to the Leader or a Character
+    pub fn give_rested_don(
+        &mut self,
+        target: InstanceId,
+        count: usize,
+        events: &mut Vec<GameEvent>,
+    ) -> Result<(), RuleError> {
+        let player = self.owner_of(target).ok_or(RuleError::NotOnField(target))?;
+        let state = self.player_mut(player);
+        let count = count.min(state.don.rested);
+        let card = state
+            .battlers_mut()
+            .find(|card| card.instance() == target)
+            .ok_or(RuleError::NotOnField(target))?;
+        card.attached_don += count;
+        state.don.rested -= count;
+
+        for _ in 0..count {
+            events.push(GameEvent::DonAttached { player, target });
+        }
+        Ok(())
+    }
+
+    /// Returns a Character to its owner's hand
+    pub fn return_to_hand(&mut self, instance: InstanceId) -> Result<(), RuleError> {
+        let player = self
+            .owner_of(instance)
+            .ok_or(RuleError::NotOnField(instance))?;
+        let state = self.player_mut(player);
+        let position = state
+            .characters
+            .iter()
+            .position(|card| card.instance() == instance)
+            .ok_or(RuleError::NotOnField(instance))?;
+
+        let character = state.characters.remove(position);
+        state.don.rested += character.attached_don;
+        state.hand.push(character.card);
+        Ok(())
+    }
+
+    pub fn trash_from_hand(
+        &mut self,
+        player: PlayerId,
+        instance: InstanceId,
+        events: &mut Vec<GameEvent>,
+    ) -> Result<(), RuleError> {
+        let state = self.player_mut(player);
+        let position = state
+            .hand_position(instance)
+            .ok_or(RuleError::NotInHand(instance))?;
+        let card = state.hand.remove(position);
+        state.trash.push(card);
+
+        events.push(GameEvent::CardTrashed {
+            player,
+            card: instance,
+        });
+        Ok(())
+    }
+}
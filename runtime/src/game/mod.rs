@@ -0,0 +1,600 @@
+//! Headless game state and rules engine.
+//!
+//! A [`GameState`] is advanced exclusively through [`GameState::apply`], which validates an
+//! [`Action`] against the rules and returns the [`GameEvent`]s it produced. Phases that don't
+//! require a decision (Refresh, Draw and DON!!) are run automatically, so the game always rests
+//! either in the Main phase of the turn player or inside a battle waiting for the defender.
+
+use std::{collections::HashSet, sync::Arc};
+
+use data::{effect::Timing, CardData, CardType};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use thiserror::Error;
+
+mod battle;
+mod effects;
+mod player;
+#[cfg(test)]
+mod tests;
+
+pub use battle::{Battle, BattleStep};
+pub use player::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PlayerId {
+    First,
+    Second,
+}
+
+impl PlayerId {
+    pub fn opponent(self) -> Self {
+        match self {
+            Self::First => Self::Second,
+            Self::Second => Self::First,
+        }
+    }
+
+    pub fn index(self) -> usize {
+        match self {
+            Self::First => 0,
+            Self::Second => 1,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Phase {
+    Refresh,
+    Draw,
+    Don,
+    Main,
+    End,
+}
+
+/// A decision made by a player
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Play a card from hand. When the character area is full, `replace` names the Character
+    /// that is trashed to make room, and must be `None` otherwise.
+    PlayCard {
+        card: InstanceId,
+        replace: Option<InstanceId>,
+    },
+    /// Give one active DON!! card from the cost area to the Leader or a Character
+    AttachDon(InstanceId),
+    Attack {
+        attacker: InstanceId,
+        target: InstanceId,
+    },
+    Block(InstanceId),
+    SkipBlock,
+    /// Trash a Character with a counter value, or play a [Counter] Event, from hand
+    Counter(InstanceId),
+    EndCounter,
+    EndTurn,
+}
+
+/// Something that happened as the result of an [`Action`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    TurnStarted {
+        player: PlayerId,
+        turn: usize,
+    },
+    PhaseChanged(Phase),
+    CardDrawn {
+        player: PlayerId,
+        card: InstanceId,
+    },
+    DonAdded {
+        player: PlayerId,
+        count: usize,
+    },
+    CardPlayed {
+        player: PlayerId,
+        card: InstanceId,
+    },
+    DonAttached {
+        player: PlayerId,
+        target: InstanceId,
+    },
+    AttackDeclared {
+        attacker: InstanceId,
+        target: InstanceId,
+    },
+    Blocked {
+        blocker: InstanceId,
+    },
+    CounterUsed {
+        player: PlayerId,
+        card: InstanceId,
+        power: isize,
+    },
+    LifeLost {
+        player: PlayerId,
+        card: InstanceId,
+        trigger: bool,
+    },
+    KnockedOut {
+        player: PlayerId,
+        card: InstanceId,
+    },
+    CardTrashed {
+        player: PlayerId,
+        card: InstanceId,
+    },
+    BattleEnded,
+    GameOver {
+        winner: PlayerId,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum SetupError {
+    #[error("{0} is not a Leader card")]
+    NotALeader(data::CardId),
+
+    #[error("{0} is a Leader card and cannot be placed in the deck")]
+    LeaderInDeck(data::CardId),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RuleError {
+    #[error("The game is already over")]
+    GameOver,
+
+    #[error("It is not the Main phase")]
+    NotMainPhase,
+
+    #[error("This action cannot be taken during a battle")]
+    InBattle,
+
+    #[error("This action can only be taken during a battle")]
+    NotInBattle,
+
+    #[error("The battle is not in the {0:?} step")]
+    WrongBattleStep(BattleStep),
+
+    #[error("{0:?} is not in the acting player's hand")]
+    NotInHand(InstanceId),
+
+    #[error("{0:?} is not on the acting player's field")]
+    NotOnField(InstanceId),
+
+    #[error("{0:?} is not a valid attack target")]
+    InvalidTarget(InstanceId),
+
+    #[error("Not enough active DON!! (need {needed}, have {available})")]
+    NotEnoughDon { needed: usize, available: usize },
+
+    #[error("The character area is full")]
+    CharacterAreaFull,
+
+    #[error("The character area is not full, so no Character can be replaced")]
+    CharacterAreaNotFull,
+
+    #[error("{0:?} cannot be played this way")]
+    CannotPlay(InstanceId),
+
+    #[error("{0:?} cannot attack")]
+    CannotAttack(InstanceId),
+
+    #[error("Players cannot attack during their first turn")]
+    FirstTurnAttack,
+
+    #[error("{0:?} cannot block")]
+    CannotBlock(InstanceId),
+
+    #[error("{0:?} cannot be used as a counter")]
+    CannotCounter(InstanceId),
+}
+
+/// The cards a player brings to a game
+#[derive(Debug, Clone)]
+pub struct PlayerSetup {
+    pub leader: Arc<CardData>,
+    pub deck: Vec<Arc<CardData>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameState {
+    pub players: [PlayerState; 2],
+    /// Number of turns started so far, across both players
+    pub turn: usize,
+    pub active: PlayerId,
+    pub phase: Phase,
+    pub battle: Option<Battle>,
+    pub winner: Option<PlayerId>,
+    once_per_turn: HashSet<InstanceId>,
+    rng: StdRng,
+}
+
+impl GameState {
+    /// Shuffles both decks, deals opening hands and Life cards and starts the first turn
+    pub fn new(setups: [PlayerSetup; 2], seed: u64) -> Result<Self, SetupError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut next_instance = 0u32;
+        let mut new_card = |data: Arc<CardData>| {
+            next_instance += 1;
+            Card::new(InstanceId(next_instance), data)
+        };
+
+        let mut players = vec![];
+        for setup in setups {
+            if setup.leader.ty != CardType::Leader {
+                return Err(SetupError::NotALeader(setup.leader.id));
+            }
+
+            if let Some(card) = setup.deck.iter().find(|c| c.ty == CardType::Leader) {
+                return Err(SetupError::LeaderInDeck(card.id));
+            }
+
+            let life = setup.leader.cost_life;
+            let leader = new_card(setup.leader);
+            let mut deck: Vec<Card> = setup.deck.into_iter().map(&mut new_card).collect();
+            deck.shuffle(&mut rng);
+
+            let mut player = PlayerState::new(leader, deck);
+            for _ in 0..STARTING_HAND_SIZE {
+                player.draw();
+            }
+
+            for _ in 0..life {
+                if let Some(card) = player.deck.pop() {
+                    player.life.push(card);
+                }
+            }
+
+            players.push(player);
+        }
+
+        let [first, second]: [PlayerState; 2] = players.try_into().unwrap();
+        let mut state = Self {
+            players: [first, second],
+            turn: 0,
+            active: PlayerId::First,
+            phase: Phase::Refresh,
+            battle: None,
+            winner: None,
+            once_per_turn: HashSet::new(),
+            rng,
+        };
+
+        let mut events = vec![];
+        state.start_turn(PlayerId::First, &mut events);
+        Ok(state)
+    }
+
+    pub fn player(&self, id: PlayerId) -> &PlayerState {
+        &self.players[id.index()]
+    }
+
+    pub fn player_mut(&mut self, id: PlayerId) -> &mut PlayerState {
+        &mut self.players[id.index()]
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.winner.is_some()
+    }
+
+    /// The player who must make the next decision
+    pub fn priority(&self) -> PlayerId {
+        match self.battle {
+            Some(_) => self.active.opponent(),
+            None => self.active,
+        }
+    }
+
+    /// Returns the player whose field holds the given card
+    pub fn owner_of(&self, instance: InstanceId) -> Option<PlayerId> {
+        [PlayerId::First, PlayerId::Second]
+            .into_iter()
+            .find(|id| self.player(*id).field_card(instance).is_some())
+    }
+
+    /// Returns the owner of a card in any zone, along with the card itself
+    pub fn find_card(&self, instance: InstanceId) -> Option<(PlayerId, &Card)> {
+        [PlayerId::First, PlayerId::Second]
+            .into_iter()
+            .find_map(|id| self.player(id).find_card(instance).map(|card| (id, card)))
+    }
+
+    /// Marks a [Once Per Turn] ability of the given card as used, returning `false` if it was
+    /// already used this turn
+    pub fn use_once_per_turn(&mut self, instance: InstanceId) -> bool {
+        self.once_per_turn.insert(instance)
+    }
+
+    /// Deterministic random number generator for this game, for use by effects
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Whether the given player is the one taking their turn
+    pub fn is_turn_of(&self, player: PlayerId) -> bool {
+        self.active == player
+    }
+
+    /// Current power of a card on the field, if it is on the field
+    pub fn power_of(&self, instance: InstanceId) -> Option<isize> {
+        let owner = self.owner_of(instance)?;
+        self.player(owner)
+            .field_card(instance)
+            .map(|card| card.power(self.is_turn_of(owner)))
+    }
+
+    pub fn apply(&mut self, action: Action) -> Result<Vec<GameEvent>, RuleError> {
+        if self.is_over() {
+            return Err(RuleError::GameOver);
+        }
+
+        let mut events = vec![];
+        match action {
+            Action::PlayCard { card, replace } => self.play_card(card, replace, &mut events)?,
+            Action::AttachDon(target) => self.attach_don(target, &mut events)?,
+            Action::Attack { attacker, target } => self.attack(attacker, target, &mut events)?,
+            Action::Block(blocker) => self.block(Some(blocker), &mut events)?,
+            Action::SkipBlock => self.block(None, &mut events)?,
+            Action::Counter(card) => self.counter(card, &mut events)?,
+            Action::EndCounter => self.resolve_damage(&mut events)?,
+            Action::EndTurn => self.end_turn(&mut events)?,
+        }
+
+        Ok(events)
+    }
+
+    /// Every action the player with priority may currently take
+    pub fn legal_actions(&self) -> Vec<Action> {
+        if self.is_over() {
+            return vec![];
+        }
+
+        if let Some(battle) = &self.battle {
+            return self.legal_battle_actions(battle);
+        }
+
+        let me = self.player(self.active);
+        let opponent = self.player(self.active.opponent());
+        let mut actions = vec![Action::EndTurn];
+
+        for card in me.hand.iter() {
+            if card.cost() > me.don.active {
+                continue;
+            }
+
+            match card.ty() {
+                CardType::Character if me.characters.len() >= MAX_CHARACTERS => {
+                    actions.extend(me.characters.iter().map(|c| Action::PlayCard {
+                        card: card.instance,
+                        replace: Some(c.instance()),
+                    }));
+                }
+                // Events without a [Main] effect, like [Counter] Events, are only used in battle
+                CardType::Event if !card.has_timing(Timing::Main) => {}
+                CardType::Character | CardType::Stage | CardType::Event => {
+                    actions.push(Action::PlayCard {
+                        card: card.instance,
+                        replace: None,
+                    })
+                }
+                CardType::Leader => {}
+            }
+        }
+
+        if me.don.active > 0 {
+            actions.extend(me.battlers().map(|c| Action::AttachDon(c.instance())));
+        }
+
+        for attacker in me.battlers().filter(|c| self.can_attack(c).is_ok()) {
+            for target in opponent.battlers() {
+                if target.instance() == opponent.leader.instance() || target.rested {
+                    actions.push(Action::Attack {
+                        attacker: attacker.instance(),
+                        target: target.instance(),
+                    });
+                }
+            }
+        }
+
+        actions
+    }
+
+    fn start_turn(&mut self, player: PlayerId, events: &mut Vec<GameEvent>) {
+        self.active = player;
+        self.turn += 1;
+        events.push(GameEvent::TurnStarted {
+            player,
+            turn: self.turn,
+        });
+
+        // Refresh phase
+        self.set_phase(Phase::Refresh, events);
+        let state = self.player_mut(player);
+        let mut returned = 0;
+        for card in state.battlers_mut() {
+            returned += card.attached_don;
+            card.attached_don = 0;
+            card.rested = false;
+        }
+        if let Some(stage) = state.stage.as_mut() {
+            stage.rested = false;
+        }
+        state.don.active += state.don.rested + returned;
+        state.don.rested = 0;
+
+        // Draw phase. The player going first skips their first draw.
+        self.set_phase(Phase::Draw, events);
+        if self.turn > 1 {
+            self.draw_cards(player, 1, events);
+            if self.is_over() {
+                return;
+            }
+        }
+
+        // DON!! phase. The player going first only receives one DON!! on their first turn.
+        self.set_phase(Phase::Don, events);
+        let wanted = if self.turn == 1 { 1 } else { 2 };
+        let state = self.player_mut(player);
+        let count = wanted.min(state.don.deck);
+        state.don.deck -= count;
+        state.don.active += count;
+        if count > 0 {
+            events.push(GameEvent::DonAdded { player, count });
+        }
+
+        self.set_phase(Phase::Main, events);
+    }
+
+    fn end_turn(&mut self, events: &mut Vec<GameEvent>) -> Result<(), RuleError> {
+        self.require_main()?;
+        self.set_phase(Phase::End, events);
+
+        for player in self.players.iter_mut() {
+            for card in player.battlers_mut() {
+                card.expire_modifiers(Expiry::EndOfTurn);
+                card.played_this_turn = false;
+            }
+        }
+        self.once_per_turn.clear();
+
+        self.start_turn(self.active.opponent(), events);
+        Ok(())
+    }
+
+    fn set_phase(&mut self, phase: Phase, events: &mut Vec<GameEvent>) {
+        self.phase = phase;
+        events.push(GameEvent::PhaseChanged(phase));
+    }
+
+    fn lose(&mut self, player: PlayerId, events: &mut Vec<GameEvent>) {
+        let winner = player.opponent();
+        self.winner = Some(winner);
+        events.push(GameEvent::GameOver { winner });
+    }
+
+    fn require_main(&self) -> Result<(), RuleError> {
+        if self.phase != Phase::Main {
+            return Err(RuleError::NotMainPhase);
+        }
+
+        if self.battle.is_some() {
+            return Err(RuleError::InBattle);
+        }
+
+        Ok(())
+    }
+
+    fn pay(&mut self, player: PlayerId, cost: usize) -> Result<(), RuleError> {
+        let don = &mut self.player_mut(player).don;
+        if don.active < cost {
+            return Err(RuleError::NotEnoughDon {
+                needed: cost,
+                available: don.active,
+            });
+        }
+
+        don.active -= cost;
+        don.rested += cost;
+        Ok(())
+    }
+
+    fn play_card(
+        &mut self,
+        instance: InstanceId,
+        replace: Option<InstanceId>,
+        events: &mut Vec<GameEvent>,
+    ) -> Result<(), RuleError> {
+        self.require_main()?;
+        let player = self.active;
+        let state = self.player(player);
+        let position = state
+            .hand_position(instance)
+            .ok_or(RuleError::NotInHand(instance))?;
+        let card = &state.hand[position];
+        let ty = card.ty();
+        let cost = card.cost();
+
+        match ty {
+            CardType::Leader => return Err(RuleError::CannotPlay(instance)),
+            CardType::Character => {
+                let full = state.characters.len() >= MAX_CHARACTERS;
+                match replace {
+                    Some(_) if !full => return Err(RuleError::CharacterAreaNotFull),
+                    Some(replaced)
+                        if !state.characters.iter().any(|c| c.instance() == replaced) =>
+                    {
+                        return Err(RuleError::NotOnField(replaced))
+                    }
+                    None if full => return Err(RuleError::CharacterAreaFull),
+                    _ => {}
+                }
+            }
+            CardType::Event if !card.has_timing(Timing::Main) => {
+                return Err(RuleError::CannotPlay(instance))
+            }
+            CardType::Stage | CardType::Event => {}
+        }
+
+        self.pay(player, cost)?;
+        let state = self.player_mut(player);
+        let card = state.hand.remove(position);
+
+        match ty {
+            CardType::Character => {
+                if let Some(replaced) = replace.and_then(|r| state.trash_character(r)) {
+                    events.push(GameEvent::CardTrashed {
+                        player,
+                        card: replaced.instance,
+                    });
+                }
+
+                let mut field = FieldCard::new(card);
+                field.played_this_turn = true;
+                state.characters.push(field);
+            }
+            CardType::Stage => {
+                if let Some(old) = state.stage.replace(FieldCard::new(card)) {
+                    events.push(GameEvent::CardTrashed {
+                        player,
+                        card: old.instance(),
+                    });
+                    state.trash.push(old.card);
+                }
+            }
+            CardType::Event => state.trash.push(card),
+            CardType::Leader => unreachable!(),
+        }
+
+        events.push(GameEvent::CardPlayed {
+            player,
+            card: instance,
+        });
+        Ok(())
+    }
+
+    fn attach_don(
+        &mut self,
+        target: InstanceId,
+        events: &mut Vec<GameEvent>,
+    ) -> Result<(), RuleError> {
+        self.require_main()?;
+        let player = self.active;
+        let state = self.player_mut(player);
+        if state.don.active == 0 {
+            return Err(RuleError::NotEnoughDon {
+                needed: 1,
+                available: 0,
+            });
+        }
+
+        let card = state
+            .battlers_mut()
+            .find(|card| card.instance() == target)
+            .ok_or(RuleError::NotOnField(target))?;
+        card.attached_don += 1;
+        state.don.active -= 1;
+
+        events.push(GameEvent::DonAttached { player, target });
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use std::{str::FromStr, sync::Arc};
+
+use data::{
+    effect::{Effect, Keyword, Timing},
+    CardData, CardType,
+};
+
+/// Maximum number of Characters a player may have in their character area
+pub const MAX_CHARACTERS: usize = 5;
+
+/// Number of cards in a DON!! deck
+pub const DON_DECK_SIZE: usize = 10;
+
+/// Number of cards each player draws before the first turn
+pub const STARTING_HAND_SIZE: usize = 5;
+
+/// Power granted by each DON!! card attached to a card during its owner's turn
+pub const DON_POWER: isize = 1000;
+
+/// Identifies a single physical card for the duration of a game. Two copies of the same
+/// [`data::CardId`] receive different instance ids.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InstanceId(pub u32);
+
+#[derive(Debug, Clone)]
+pub struct Card {
+    pub instance: InstanceId,
+    pub data: Arc<CardData>,
+    pub keywords: Vec<Keyword>,
+}
+
+impl Card {
+    pub fn new(instance: InstanceId, data: Arc<CardData>) -> Self {
+        // Keywords are read line by line so that a single line the effect parser does not
+        // understand yet doesn't hide a [Blocker] or [Rush] printed on another line.
+        let keywords = data
+            .effect
+            .iter()
+            .flat_map(|effect| effect.lines())
+            .filter_map(|line| Effect::from_str(line).ok())
+            .flat_map(|effect| effect.abilities)
+            .filter(|ability| ability.timings.is_empty() && ability.don_requirement.is_none())
+            .flat_map(|ability| ability.keywords)
+            .collect();
+
+        Self {
+            instance,
+            data,
+            keywords,
+        }
+    }
+
+    pub fn has_keyword(&self, keyword: Keyword) -> bool {
+        self.keywords.contains(&keyword)
+    }
+
+    /// Whether one of the card's effects starts with the given timing, e.g. `[Main]`. Only the
+    /// timing labels are read, so this also covers effects the parser does not support yet.
+    pub fn has_timing(&self, timing: Timing) -> bool {
+        self.data
+            .effect
+            .iter()
+            .flat_map(|effect| effect.lines())
+            .filter_map(|line| line.trim_start().strip_prefix('['))
+            .filter_map(|line| line.split_once(']'))
+            .any(|(label, _)| Timing::from_str(label).ok() == Some(timing))
+    }
+
+    pub fn cost(&self) -> usize {
+        self.data.cost_life
+    }
+
+    pub fn counter(&self) -> usize {
+        self.data.counter.unwrap_or(0)
+    }
+
+    pub fn ty(&self) -> CardType {
+        self.data.ty
+    }
+}
+
+/// When a temporary power modifier stops applying
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Expiry {
+    EndOfBattle,
+    EndOfTurn,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PowerModifier {
+    pub amount: isize,
+    pub expires: Expiry,
+}
+
+/// A Leader, Character or Stage that is on the field
+#[derive(Debug, Clone)]
+pub struct FieldCard {
+    pub card: Card,
+    pub rested: bool,
+    pub attached_don: usize,
+    pub played_this_turn: bool,
+    pub power_modifiers: Vec<PowerModifier>,
+}
+
+impl FieldCard {
+    pub fn new(card: Card) -> Self {
+        Self {
+            card,
+            rested: false,
+            attached_don: 0,
+            played_this_turn: false,
+            power_modifiers: vec![],
+        }
+    }
+
+    pub fn instance(&self) -> InstanceId {
+        self.card.instance
+    }
+
+    /// Current power of the card. Attached DON!! only count during their owner's turn.
+    pub fn power(&self, owners_turn: bool) -> isize {
+        let base = self.card.data.power.unwrap_or(0) as isize;
+        let don = if owners_turn {
+            self.attached_don as isize * DON_POWER
+        } else {
+            0
+        };
+
+        base + don + self.power_modifiers.iter().map(|m| m.amount).sum::<isize>()
+    }
+
+    pub fn expire_modifiers(&mut self, expires: Expiry) {
+        self.power_modifiers.retain(|m| m.expires != expires);
+    }
+}
+
+/// DON!! cards are interchangeable, so only the counts of each zone are tracked. DON!! attached
+/// to a card are stored on the [`FieldCard`] itself.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DonArea {
+    pub deck: usize,
+    pub active: usize,
+    pub rested: usize,
+}
+
+impl Default for DonArea {
+    fn default() -> Self {
+        Self {
+            deck: DON_DECK_SIZE,
+            active: 0,
+            rested: 0,
+        }
+    }
+}
+
+/// Everything one player owns. For `deck` and `life` the last element is the top card.
+#[derive(Debug, Clone)]
+pub struct PlayerState {
+    pub leader: FieldCard,
+    pub life: Vec<Card>,
+    pub hand: Vec<Card>,
+    pub deck: Vec<Card>,
+    pub trash: Vec<Card>,
+    pub characters: Vec<FieldCard>,
+    pub stage: Option<FieldCard>,
+    pub don: DonArea,
+}
+
+impl PlayerState {
+    pub fn new(leader: Card, deck: Vec<Card>) -> Self {
+        Self {
+            leader: FieldCard::new(leader),
+            life: vec![],
+            hand: vec![],
+            deck,
+            trash: vec![],
+            characters: vec![],
+            stage: None,
+            don: DonArea::default(),
+        }
+    }
+
+    /// Moves the top card of the deck into the hand, returning `None` if the deck is empty
+    pub fn draw(&mut self) -> Option<InstanceId> {
+        let card = self.deck.pop()?;
+        let instance = card.instance;
+        self.hand.push(card);
+        Some(instance)
+    }
+
+    pub fn field_card(&self, instance: InstanceId) -> Option<&FieldCard> {
+        std::iter::once(&self.leader)
+            .chain(self.characters.iter())
+            .chain(self.stage.iter())
+            .find(|card| card.instance() == instance)
+    }
+
+    pub fn field_card_mut(&mut self, instance: InstanceId) -> Option<&mut FieldCard> {
+        std::iter::once(&mut self.leader)
+            .chain(self.characters.iter_mut())
+            .chain(self.stage.iter_mut())
+            .find(|card| card.instance() == instance)
+    }
+
+    /// Looks for a card in every zone of this player
+    pub fn find_card(&self, instance: InstanceId) -> Option<&Card> {
+        if let Some(card) = self.field_card(instance) {
+            return Some(&card.card);
+        }
+
+        self.hand
+            .iter()
+            .chain(self.life.iter())
+            .chain(self.deck.iter())
+            .chain(self.trash.iter())
+            .find(|card| card.instance == instance)
+    }
+
+    pub fn hand_position(&self, instance: InstanceId) -> Option<usize> {
+        self.hand.iter().position(|card| card.instance == instance)
+    }
+
+    /// Leader and Characters, the cards that can attack, be attacked and hold DON!!
+    pub fn battlers(&self) -> impl Iterator<Item = &FieldCard> {
+        std::iter::once(&self.leader).chain(self.characters.iter())
+    }
+
+    pub fn battlers_mut(&mut self) -> impl Iterator<Item = &mut FieldCard> {
+        std::iter::once(&mut self.leader).chain(self.characters.iter_mut())
+    }
+
+    /// Removes a Character from the field, returning its attached DON!! to the cost area rested
+    /// and placing the card in the trash
+    pub fn trash_character(&mut self, instance: InstanceId) -> Option<Card> {
+        let position = self
+            .characters
+            .iter()
+            .position(|card| card.instance() == instance)?;
+        let character = self.characters.remove(position);
+        self.don.rested += character.attached_don;
+        self.trash.push(character.card.clone());
+        Some(character.card)
+    }
+
+    pub fn total_don(&self) -> usize {
+        self.don.active
+            + self.don.rested
+            + self.battlers().map(|card| card.attached_don).sum::<usize>()
+    }
+}
@@ -0,0 +1,670 @@
+use std::sync::Arc;
+
+use data::{CardData, CardId, CardType, Color, Rarity, SetId};
+
+use super::*;
+
+fn card(number: usize, ty: CardType, cost_life: usize, power: usize) -> CardData {
+    CardData {
+        id: CardId {
+            set: SetId::Starter(1),
+            card: number,
+        },
+        release_set: SetId::Starter(1),
+        rarity: Rarity::Common,
+        ty,
+        name: format!("Card {number}"),
+        image_name: format!("ST01-{number:03}.png"),
+        cost_life,
+        power: (ty != CardType::Event).then_some(power),
+        counter: None,
+        color: vec![Color::Red],
+        effect: None,
+        trigger: None,
+        subtype: vec![],
+        attribute: vec![],
+        printings: vec![],
+        products: vec![],
+        errata: vec![],
+        text_history: vec![],
+    }
+}
+
+fn leader() -> Arc<CardData> {
+    Arc::new(card(1, CardType::Leader, 2, 5000))
+}
+
+fn character(cost: usize, power: usize) -> Arc<CardData> {
+    Arc::new(card(2, CardType::Character, cost, power))
+}
+
+fn with_counter(counter: usize) -> Arc<CardData> {
+    Arc::new(CardData {
+        counter: Some(counter),
+        ..card(3, CardType::Character, 2, 3000)
+    })
+}
+
+fn with_effect(ty: CardType, cost: usize, power: usize, effect: &str) -> Arc<CardData> {
+    Arc::new(CardData {
+        effect: Some(effect.to_string()),
+        ..card(4, ty, cost, power)
+    })
+}
+
+/// A game between two players with a 5000 power Leader with 2 Life and a deck of vanilla
+/// Characters, in the Main phase of the first turn
+fn game() -> GameState {
+    let setup = || PlayerSetup {
+        leader: leader(),
+        deck: (0..20).map(|_| character(1, 3000)).collect(),
+    };
+    GameState::new([setup(), setup()], 0).unwrap()
+}
+
+fn add_to_hand(
+    state: &mut GameState,
+    player: PlayerId,
+    id: u32,
+    data: Arc<CardData>,
+) -> InstanceId {
+    let instance = InstanceId(id);
+    state
+        .player_mut(player)
+        .hand
+        .push(Card::new(instance, data));
+    instance
+}
+
+fn add_to_field(
+    state: &mut GameState,
+    player: PlayerId,
+    id: u32,
+    data: Arc<CardData>,
+) -> InstanceId {
+    let instance = InstanceId(id);
+    state
+        .player_mut(player)
+        .characters
+        .push(FieldCard::new(Card::new(instance, data)));
+    instance
+}
+
+fn leader_of(state: &GameState, player: PlayerId) -> InstanceId {
+    state.player(player).leader.instance()
+}
+
+/// Ends turns until the first player starts their second turn, the first one they may attack in
+fn skip_to_first_attack(state: &mut GameState) {
+    state.apply(Action::EndTurn).unwrap();
+    state.apply(Action::EndTurn).unwrap();
+    assert_eq!(state.turn, 3);
+}
+
+#[test]
+fn setup_deals_hands_and_life() {
+    let state = game();
+
+    for player in [PlayerId::First, PlayerId::Second] {
+        let state = state.player(player);
+        assert_eq!(state.life.len(), 2);
+        assert_eq!(state.hand.len(), STARTING_HAND_SIZE);
+        assert_eq!(state.deck.len(), 20 - STARTING_HAND_SIZE - 2);
+    }
+
+    // The first player does not draw and only receives 1 DON!! on their first turn
+    assert_eq!(state.turn, 1);
+    assert_eq!(state.active, PlayerId::First);
+    assert_eq!(state.phase, Phase::Main);
+    assert_eq!(state.player(PlayerId::First).don.active, 1);
+}
+
+#[test]
+fn setup_rejects_leaders_in_the_deck() {
+    let setup = PlayerSetup {
+        leader: leader(),
+        deck: vec![leader()],
+    };
+
+    assert!(matches!(
+        GameState::new([setup.clone(), setup], 0),
+        Err(SetupError::LeaderInDeck(_))
+    ));
+}
+
+#[test]
+fn turn_runs_through_every_phase() {
+    let mut state = game();
+    let top = state.player(PlayerId::Second).deck.last().unwrap().instance;
+
+    assert_eq!(
+        state.apply(Action::EndTurn).unwrap(),
+        vec![
+            GameEvent::PhaseChanged(Phase::End),
+            GameEvent::TurnStarted {
+                player: PlayerId::Second,
+                turn: 2,
+            },
+            GameEvent::PhaseChanged(Phase::Refresh),
+            GameEvent::PhaseChanged(Phase::Draw),
+            GameEvent::CardDrawn {
+                player: PlayerId::Second,
+                card: top,
+            },
+            GameEvent::PhaseChanged(Phase::Don),
+            GameEvent::DonAdded {
+                player: PlayerId::Second,
+                count: 2,
+            },
+            GameEvent::PhaseChanged(Phase::Main),
+        ]
+    );
+    assert_eq!(state.active, PlayerId::Second);
+    assert_eq!(state.priority(), PlayerId::Second);
+    assert_eq!(state.player(PlayerId::Second).hand.len(), 6);
+}
+
+#[test]
+fn refresh_returns_don_and_sets_cards_active() {
+    let mut state = game();
+    skip_to_first_attack(&mut state);
+
+    let me = PlayerId::First;
+    let leader = leader_of(&state, me);
+    let character = add_to_hand(&mut state, me, 100, character(1, 3000));
+    state.apply(Action::AttachDon(leader)).unwrap();
+    state
+        .apply(Action::PlayCard {
+            card: character,
+            replace: None,
+        })
+        .unwrap();
+    state.player_mut(me).leader.rested = true;
+
+    let don = state.player(me).don;
+    assert_eq!((don.active, don.rested), (1, 1));
+    assert_eq!(state.player(me).total_don(), 3);
+
+    state.apply(Action::EndTurn).unwrap();
+    state.apply(Action::EndTurn).unwrap();
+
+    let state = state.player(me);
+    assert!(!state.leader.rested);
+    assert_eq!(state.leader.attached_don, 0);
+    assert_eq!((state.don.active, state.don.rested), (5, 0));
+    assert!(!state.characters[0].played_this_turn);
+}
+
+#[test]
+fn playing_a_card_pays_its_cost() {
+    let mut state = game();
+    let me = PlayerId::First;
+    let expensive = add_to_hand(&mut state, me, 100, character(2, 4000));
+    let cheap = add_to_hand(&mut state, me, 101, character(1, 3000));
+
+    assert_eq!(
+        state.apply(Action::PlayCard {
+            card: expensive,
+            replace: None,
+        }),
+        Err(RuleError::NotEnoughDon {
+            needed: 2,
+            available: 1,
+        })
+    );
+    assert!(state.player(me).hand_position(expensive).is_some());
+
+    let events = state
+        .apply(Action::PlayCard {
+            card: cheap,
+            replace: None,
+        })
+        .unwrap();
+    assert_eq!(
+        events,
+        vec![GameEvent::CardPlayed {
+            player: me,
+            card: cheap,
+        }]
+    );
+
+    let state = state.player(me);
+    assert_eq!((state.don.active, state.don.rested), (0, 1));
+    assert!(state.characters[0].played_this_turn);
+    assert!(state.hand_position(cheap).is_none());
+}
+
+#[test]
+fn counter_events_cannot_be_played_in_the_main_phase() {
+    let mut state = game();
+    let me = PlayerId::First;
+    let main = add_to_hand(
+        &mut state,
+        me,
+        100,
+        with_effect(CardType::Event, 1, 0, "[Main] Draw 1 card."),
+    );
+    let counter = add_to_hand(
+        &mut state,
+        me,
+        101,
+        with_effect(
+            CardType::Event,
+            1,
+            0,
+            "[Counter] Up to 1 of your Leader or Character cards gains +2000 power during this \
+             battle.",
+        ),
+    );
+
+    let actions = state.legal_actions();
+    assert!(actions.contains(&Action::PlayCard {
+        card: main,
+        replace: None,
+    }));
+    assert!(!actions.contains(&Action::PlayCard {
+        card: counter,
+        replace: None,
+    }));
+
+    assert_eq!(
+        state.apply(Action::PlayCard {
+            card: counter,
+            replace: None,
+        }),
+        Err(RuleError::CannotPlay(counter))
+    );
+    assert_eq!(state.player(me).don.active, 1);
+    state
+        .apply(Action::PlayCard {
+            card: main,
+            replace: None,
+        })
+        .unwrap();
+    assert_eq!(state.player(me).trash.last().unwrap().instance, main);
+}
+
+#[test]
+fn attaching_don_adds_power_during_own_turn() {
+    let mut state = game();
+    let me = PlayerId::First;
+    let leader = leader_of(&state, me);
+    let not_on_field = add_to_hand(&mut state, me, 100, character(1, 3000));
+
+    assert_eq!(
+        state.apply(Action::AttachDon(not_on_field)),
+        Err(RuleError::NotOnField(not_on_field))
+    );
+    assert_eq!(
+        state.apply(Action::AttachDon(leader)).unwrap(),
+        vec![GameEvent::DonAttached {
+            player: me,
+            target: leader,
+        }]
+    );
+    assert_eq!(state.power_of(leader), Some(6000));
+    assert_eq!(
+        state.apply(Action::AttachDon(leader)),
+        Err(RuleError::NotEnoughDon {
+            needed: 1,
+            available: 0,
+        })
+    );
+
+    // Attached DON!! only count during their owner's turn
+    state.apply(Action::EndTurn).unwrap();
+    assert_eq!(state.power_of(leader), Some(5000));
+}
+
+#[test]
+fn cannot_attack_during_first_turns() {
+    let mut state = game();
+    let attack = Action::Attack {
+        attacker: leader_of(&state, PlayerId::First),
+        target: leader_of(&state, PlayerId::Second),
+    };
+
+    assert_eq!(state.apply(attack), Err(RuleError::FirstTurnAttack));
+    state.apply(Action::EndTurn).unwrap();
+    assert_eq!(
+        state.apply(Action::Attack {
+            attacker: leader_of(&state, PlayerId::Second),
+            target: leader_of(&state, PlayerId::First),
+        }),
+        Err(RuleError::FirstTurnAttack)
+    );
+}
+
+#[test]
+fn attack_on_leader_takes_life() {
+    let mut state = game();
+    skip_to_first_attack(&mut state);
+
+    let attacker = leader_of(&state, PlayerId::First);
+    let target = leader_of(&state, PlayerId::Second);
+    let active = add_to_field(&mut state, PlayerId::Second, 100, character(1, 3000));
+
+    assert_eq!(
+        state.apply(Action::Attack {
+            attacker,
+            target: active,
+        }),
+        Err(RuleError::InvalidTarget(active))
+    );
+
+    state.apply(Action::Attack { attacker, target }).unwrap();
+    assert_eq!(state.priority(), PlayerId::Second);
+    assert_eq!(state.apply(Action::EndTurn), Err(RuleError::InBattle));
+    assert_eq!(
+        state.apply(Action::EndCounter),
+        Err(RuleError::WrongBattleStep(BattleStep::Counter))
+    );
+
+    state.apply(Action::SkipBlock).unwrap();
+    let life = state.player(PlayerId::Second).life.last().unwrap().instance;
+    let events = state.apply(Action::EndCounter).unwrap();
+    assert_eq!(
+        events,
+        vec![
+            GameEvent::LifeLost {
+                player: PlayerId::Second,
+                card: life,
+                trigger: false,
+            },
+            GameEvent::BattleEnded,
+        ]
+    );
+
+    let defender = state.player(PlayerId::Second);
+    assert_eq!(defender.life.len(), 1);
+    assert!(defender.hand_position(life).is_some());
+    assert!(state.player(PlayerId::First).leader.rested);
+    assert!(state.battle.is_none());
+}
+
+#[test]
+fn blocker_takes_the_attack() {
+    let mut state = game();
+    skip_to_first_attack(&mut state);
+
+    let attacker = leader_of(&state, PlayerId::First);
+    let target = leader_of(&state, PlayerId::Second);
+    let blocker = add_to_field(
+        &mut state,
+        PlayerId::Second,
+        100,
+        with_effect(CardType::Character, 2, 4000, "[Blocker]"),
+    );
+    let vanilla = add_to_field(&mut state, PlayerId::Second, 101, character(1, 3000));
+
+    state.apply(Action::Attack { attacker, target }).unwrap();
+    assert_eq!(
+        state.legal_actions(),
+        vec![Action::SkipBlock, Action::Block(blocker)]
+    );
+    assert_eq!(
+        state.apply(Action::Block(vanilla)),
+        Err(RuleError::CannotBlock(vanilla))
+    );
+
+    assert_eq!(
+        state.apply(Action::Block(blocker)).unwrap(),
+        vec![GameEvent::Blocked { blocker }]
+    );
+    assert_eq!(state.battle.unwrap().target, blocker);
+
+    let events = state.apply(Action::EndCounter).unwrap();
+    assert_eq!(
+        events,
+        vec![
+            GameEvent::KnockedOut {
+                player: PlayerId::Second,
+                card: blocker,
+            },
+            GameEvent::BattleEnded,
+        ]
+    );
+
+    let defender = state.player(PlayerId::Second);
+    assert_eq!(defender.life.len(), 2);
+    assert_eq!(defender.trash.last().unwrap().instance, blocker);
+}
+
+#[test]
+fn counters_raise_power_until_the_battle_ends() {
+    let mut state = game();
+    skip_to_first_attack(&mut state);
+
+    let defender = PlayerId::Second;
+    let attacker = leader_of(&state, PlayerId::First);
+    let target = leader_of(&state, defender);
+    state.apply(Action::AttachDon(attacker)).unwrap();
+
+    let counter = add_to_hand(&mut state, defender, 100, with_counter(1000));
+    let event = add_to_hand(
+        &mut state,
+        defender,
+        101,
+        with_effect(
+            CardType::Event,
+            1,
+            0,
+            "[Counter] Up to 1 of your Leader or Character cards gains +2000 power during this \
+             battle.",
+        ),
+    );
+    let vanilla = add_to_hand(&mut state, defender, 102, character(1, 3000));
+
+    state.apply(Action::Attack { attacker, target }).unwrap();
+    state.apply(Action::SkipBlock).unwrap();
+    assert_eq!(
+        state.apply(Action::Counter(vanilla)),
+        Err(RuleError::CannotCounter(vanilla))
+    );
+
+    // 5000 + 1000 against 6000 would still hit, the attacker wins ties
+    assert_eq!(
+        state.apply(Action::Counter(counter)).unwrap(),
+        vec![GameEvent::CounterUsed {
+            player: defender,
+            card: counter,
+            power: 1000,
+        }]
+    );
+    assert_eq!(state.power_of(target), Some(6000));
+
+    let don = state.player(defender).don;
+    state.apply(Action::Counter(event)).unwrap();
+    assert_eq!(state.player(defender).don.active, don.active - 1);
+    assert_eq!(state.power_of(target), Some(8000));
+
+    let events = state.apply(Action::EndCounter).unwrap();
+    assert_eq!(events, vec![GameEvent::BattleEnded]);
+    assert_eq!(state.player(defender).life.len(), 2);
+    assert_eq!(state.power_of(target), Some(5000));
+    assert_eq!(state.player(defender).trash.len(), 2);
+}
+
+#[test]
+fn battle_ends_without_damage_when_a_card_leaves_the_field() {
+    let mut state = game();
+    skip_to_first_attack(&mut state);
+
+    let defender = PlayerId::Second;
+    let attacker = add_to_field(&mut state, PlayerId::First, 100, character(1, 6000));
+    let target = add_to_field(&mut state, defender, 101, character(1, 3000));
+    state.player_mut(defender).characters[0].rested = true;
+    let counter = add_to_hand(&mut state, defender, 102, with_counter(1000));
+
+    // The target is K.O.'d by an effect during the Counter step
+    state.apply(Action::Attack { attacker, target }).unwrap();
+    state.apply(Action::SkipBlock).unwrap();
+    state.ko(target, &mut vec![]).unwrap();
+    assert_eq!(state.legal_actions(), vec![Action::EndCounter]);
+    assert_eq!(
+        state.apply(Action::Counter(counter)),
+        Err(RuleError::InvalidTarget(target))
+    );
+    assert!(state.player(defender).hand_position(counter).is_some());
+
+    let events = state.apply(Action::EndCounter).unwrap();
+    assert_eq!(events, vec![GameEvent::BattleEnded]);
+    assert_eq!(state.battle, None);
+    assert_eq!(state.player(defender).trash.len(), 1);
+
+    // The attacker is returned to its owner's hand before the Damage step
+    state.player_mut(PlayerId::First).characters[0].rested = false;
+    let target = leader_of(&state, defender);
+    state.apply(Action::Attack { attacker, target }).unwrap();
+    state.apply(Action::SkipBlock).unwrap();
+    state.return_to_hand(attacker).unwrap();
+
+    let events = state.apply(Action::EndCounter).unwrap();
+    assert_eq!(events, vec![GameEvent::BattleEnded]);
+    assert_eq!(state.battle, None);
+    assert_eq!(state.player(defender).life.len(), 2);
+}
+
+#[test]
+fn life_triggers_are_reported() {
+    let mut state = game();
+    skip_to_first_attack(&mut state);
+
+    let defender = PlayerId::Second;
+    let trigger = Card::new(
+        InstanceId(100),
+        Arc::new(CardData {
+            trigger: Some("Draw 1 card.".to_string()),
+            ..card(5, CardType::Event, 1, 0)
+        }),
+    );
+    state.player_mut(defender).life.push(trigger);
+
+    state
+        .apply(Action::Attack {
+            attacker: leader_of(&state, PlayerId::First),
+            target: leader_of(&state, defender),
+        })
+        .unwrap();
+    state.apply(Action::SkipBlock).unwrap();
+
+    assert_eq!(
+        state.apply(Action::EndCounter).unwrap()[0],
+        GameEvent::LifeLost {
+            player: defender,
+            card: InstanceId(100),
+            trigger: true,
+        }
+    );
+}
+
+#[test]
+fn damage_without_life_loses_the_game() {
+    let mut state = game();
+    skip_to_first_attack(&mut state);
+    state.player_mut(PlayerId::Second).life.clear();
+
+    state
+        .apply(Action::Attack {
+            attacker: leader_of(&state, PlayerId::First),
+            target: leader_of(&state, PlayerId::Second),
+        })
+        .unwrap();
+    state.apply(Action::SkipBlock).unwrap();
+
+    let events = state.apply(Action::EndCounter).unwrap();
+    assert!(events.contains(&GameEvent::GameOver {
+        winner: PlayerId::First,
+    }));
+    assert_eq!(state.winner, Some(PlayerId::First));
+    assert!(state.legal_actions().is_empty());
+    assert_eq!(state.apply(Action::EndTurn), Err(RuleError::GameOver));
+}
+
+#[test]
+fn drawing_from_an_empty_deck_loses_the_game() {
+    let mut state = game();
+    state.player_mut(PlayerId::Second).deck.clear();
+
+    let events = state.apply(Action::EndTurn).unwrap();
+    assert_eq!(
+        events.last(),
+        Some(&GameEvent::GameOver {
+            winner: PlayerId::First,
+        })
+    );
+    assert!(state.is_over());
+}
+
+#[test]
+fn full_character_area_requires_a_replacement() {
+    let mut state = game();
+    let me = PlayerId::First;
+    let characters: Vec<_> = (0..MAX_CHARACTERS as u32)
+        .map(|id| add_to_field(&mut state, me, 100 + id, character(1, 3000)))
+        .collect();
+    let stage = Card::new(InstanceId(200), Arc::new(card(6, CardType::Stage, 1, 0)));
+    state.player_mut(me).stage = Some(FieldCard::new(stage));
+    let new = add_to_hand(&mut state, me, 300, character(1, 3000));
+
+    assert_eq!(
+        state.apply(Action::PlayCard {
+            card: new,
+            replace: None,
+        }),
+        Err(RuleError::CharacterAreaFull)
+    );
+
+    // Only Characters can be replaced, not the Leader or the Stage
+    for other in [leader_of(&state, me), InstanceId(200)] {
+        assert_eq!(
+            state.apply(Action::PlayCard {
+                card: new,
+                replace: Some(other),
+            }),
+            Err(RuleError::NotOnField(other))
+        );
+    }
+    assert_eq!(state.player(me).don.active, 1);
+
+    let events = state
+        .apply(Action::PlayCard {
+            card: new,
+            replace: Some(characters[0]),
+        })
+        .unwrap();
+    assert_eq!(
+        events,
+        vec![
+            GameEvent::CardTrashed {
+                player: me,
+                card: characters[0],
+            },
+            GameEvent::CardPlayed {
+                player: me,
+                card: new,
+            },
+        ]
+    );
+
+    let state = state.player(me);
+    assert_eq!(state.characters.len(), MAX_CHARACTERS);
+    assert_eq!(state.trash.last().unwrap().instance, characters[0]);
+}
+
+#[test]
+fn replacement_requires_a_full_character_area() {
+    let mut state = game();
+    let me = PlayerId::First;
+    let existing = add_to_field(&mut state, me, 100, character(1, 3000));
+    let new = add_to_hand(&mut state, me, 101, character(1, 3000));
+
+    assert_eq!(
+        state.apply(Action::PlayCard {
+            card: new,
+            replace: Some(existing),
+        }),
+        Err(RuleError::CharacterAreaNotFull)
+    );
+    assert_eq!(state.player(me).characters.len(), 1);
+}
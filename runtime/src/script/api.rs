@@ -0,0 +1,268 @@
+//! The `game` object handed to script handlers.
+//!
+//! Players are named relative to the card running the script: `"you"` is its controller and
+//! `"opponent"` the other player. Cards are referred to by their instance number, which is the
+//! `id` field of the card tables returned by queries.
+//!
+//! Queries:
+//! - `game:source()`: the card running the script
+//! - `game:turn()`, `game:is_my_turn()`
+//! - `game:life(player)`, `game:hand_size(player)`, `game:deck_size(player)`,
+//!   `game:trash_size(player)`, `game:don(player)`
+//! - `game:leader(player)`, `game:characters(player)`, `game:hand()`, `game:card(id)`
+//! - `game:once_per_turn()`: `true` the first time it is called during a turn
+//!
+//! Actions:
+//! - `game:draw(count)`, `game:ko(id)`, `game:rest(id)`, `game:set_active(id)`
+//! - `game:give_power(id, amount, "turn" | "battle")`, `game:give_don(id, count)`
+//! - `game:add_don(count, rested)`, `game:return_to_hand(id)`, `game:trash_from_hand(id)`
+//! - `game:deal_damage(player, count)`
+
+use mlua::{ExternalError, Lua, Table, UserData, UserDataMethods};
+
+use crate::game::{Card, Expiry, FieldCard, GameEvent, GameState, InstanceId, PlayerId};
+
+pub struct ScriptApi<'a> {
+    game: &'a mut GameState,
+    controller: PlayerId,
+    source: InstanceId,
+    events: &'a mut Vec<GameEvent>,
+}
+
+impl<'a> ScriptApi<'a> {
+    pub fn new(
+        game: &'a mut GameState,
+        controller: PlayerId,
+        source: InstanceId,
+        events: &'a mut Vec<GameEvent>,
+    ) -> Self {
+        Self {
+            game,
+            controller,
+            source,
+            events,
+        }
+    }
+
+    fn player(&self, name: &str) -> mlua::Result<PlayerId> {
+        match name {
+            "you" => Ok(self.controller),
+            "opponent" => Ok(self.controller.opponent()),
+            other => Err(
+                format!("unknown player '{other}', expected 'you' or 'opponent'").into_lua_err(),
+            ),
+        }
+    }
+
+    /// Only cards on the field, in the controller's hand or in a trash are visible to scripts
+    fn visible_card(&self, instance: InstanceId) -> Option<&Card> {
+        let (owner, card) = self.game.find_card(instance)?;
+        let state = self.game.player(owner);
+        let visible = state.field_card(instance).is_some()
+            || state.trash.iter().any(|c| c.instance == instance)
+            || (owner == self.controller && state.hand_position(instance).is_some());
+        visible.then_some(card)
+    }
+}
+
+fn card_table<'lua>(lua: &'lua Lua, card: &Card) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    table.set("id", card.instance.0)?;
+    table.set("card_id", card.data.id.to_string())?;
+    table.set("name", card.data.name.as_str())?;
+    table.set("type", card.data.ty.to_string())?;
+    table.set("cost", card.data.cost_life)?;
+    table.set("power", card.data.power)?;
+    table.set("counter", card.data.counter)?;
+    table.set(
+        "colors",
+        lua.create_sequence_from(card.data.color.iter().map(|c| c.to_string()))?,
+    )?;
+    table.set(
+        "subtypes",
+        lua.create_sequence_from(card.data.subtype.iter().map(|s| s.to_string()))?,
+    )?;
+    Ok(table)
+}
+
+fn field_card_table<'lua>(
+    lua: &'lua Lua,
+    card: &FieldCard,
+    owners_turn: bool,
+) -> mlua::Result<Table<'lua>> {
+    let table = card_table(lua, &card.card)?;
+    table.set("power", card.power(owners_turn))?;
+    table.set("rested", card.rested)?;
+    table.set("attached_don", card.attached_don)?;
+    Ok(table)
+}
+
+impl UserData for ScriptApi<'_> {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("source", |_, this, ()| Ok(this.source.0));
+
+        methods.add_method("turn", |_, this, ()| Ok(this.game.turn));
+
+        methods.add_method("is_my_turn", |_, this, ()| {
+            Ok(this.game.is_turn_of(this.controller))
+        });
+
+        methods.add_method("life", |_, this, player: String| {
+            Ok(this.game.player(this.player(&player)?).life.len())
+        });
+
+        methods.add_method("hand_size", |_, this, player: String| {
+            Ok(this.game.player(this.player(&player)?).hand.len())
+        });
+
+        methods.add_method("deck_size", |_, this, player: String| {
+            Ok(this.game.player(this.player(&player)?).deck.len())
+        });
+
+        methods.add_method("trash_size", |_, this, player: String| {
+            Ok(this.game.player(this.player(&player)?).trash.len())
+        });
+
+        methods.add_method("don", |lua, this, player: String| {
+            let state = this.game.player(this.player(&player)?);
+            let table = lua.create_table()?;
+            table.set("deck", state.don.deck)?;
+            table.set("active", state.don.active)?;
+            table.set("rested", state.don.rested)?;
+            Ok(table)
+        });
+
+        methods.add_method("leader", |lua, this, player: String| {
+            let player = this.player(&player)?;
+            field_card_table(
+                lua,
+                &this.game.player(player).leader,
+                this.game.is_turn_of(player),
+            )
+        });
+
+        methods.add_method("characters", |lua, this, player: String| {
+            let player = this.player(&player)?;
+            let owners_turn = this.game.is_turn_of(player);
+            let cards = this
+                .game
+                .player(player)
+                .characters
+                .iter()
+                .map(|card| field_card_table(lua, card, owners_turn))
+                .collect::<mlua::Result<Vec<_>>>()?;
+            lua.create_sequence_from(cards)
+        });
+
+        methods.add_method("hand", |lua, this, ()| {
+            let cards = this
+                .game
+                .player(this.controller)
+                .hand
+                .iter()
+                .map(|card| card_table(lua, card))
+                .collect::<mlua::Result<Vec<_>>>()?;
+            lua.create_sequence_from(cards)
+        });
+
+        methods.add_method("card", |lua, this, id: u32| {
+            let instance = InstanceId(id);
+            let Some(card) = this.visible_card(instance) else {
+                return Ok(None);
+            };
+
+            let table = match this.game.owner_of(instance) {
+                Some(owner) => field_card_table(
+                    lua,
+                    this.game.player(owner).field_card(instance).unwrap(),
+                    this.game.is_turn_of(owner),
+                )?,
+                None => card_table(lua, card)?,
+            };
+            Ok(Some(table))
+        });
+
+        methods.add_method_mut("once_per_turn", |_, this, ()| {
+            Ok(this.game.use_once_per_turn(this.source))
+        });
+
+        methods.add_method_mut("draw", |_, this, count: usize| {
+            this.game.draw_cards(this.controller, count, this.events);
+            Ok(())
+        });
+
+        methods.add_method_mut("ko", |_, this, id: u32| {
+            this.game
+                .ko(InstanceId(id), this.events)
+                .map_err(|e| e.into_lua_err())
+        });
+
+        methods.add_method_mut("rest", |_, this, id: u32| {
+            this.game
+                .set_rested(InstanceId(id), true)
+                .map_err(|e| e.into_lua_err())
+        });
+
+        methods.add_method_mut("set_active", |_, this, id: u32| {
+            this.game
+                .set_rested(InstanceId(id), false)
+                .map_err(|e| e.into_lua_err())
+        });
+
+        methods.add_method_mut(
+            "give_power",
+            |_, this, (id, amount, duration): (u32, isize, Option<String>)| {
+                let expires = match duration.as_deref() {
+                    None | Some("turn") => Expiry::EndOfTurn,
+                    Some("battle") => Expiry::EndOfBattle,
+                    Some(other) => {
+                        return Err(format!(
+                            "unknown duration '{other}', expected 'turn' or 'battle'"
+                        )
+                        .into_lua_err())
+                    }
+                };
+
+                this.game
+                    .give_power(InstanceId(id), amount, expires)
+                    .map_err(|e| e.into_lua_err())
+            },
+        );
+
+        methods.add_method_mut("give_don", |_, this, (id, count): (u32, usize)| {
+            this.game
+                .give_rested_don(InstanceId(id), count, this.events)
+                .map_err(|e| e.into_lua_err())
+        });
+
+        methods.add_method_mut(
+            "add_don",
+            |_, this, (count, rested): (usize, Option<bool>)| {
+                this.game
+                    .add_don(this.controller, count, rested.unwrap_or(false), this.events);
+                Ok(())
+            },
+        );
+
+        methods.add_method_mut("return_to_hand", |_, this, id: u32| {
+            this.game
+                .return_to_hand(InstanceId(id))
+                .map_err(|e| e.into_lua_err())
+        });
+
+        methods.add_method_mut("trash_from_hand", |_, this, id: u32| {
+            this.game
+                .trash_from_hand(this.controller, InstanceId(id), this.events)
+                .map_err(|e| e.into_lua_err())
+        });
+
+        methods.add_method_mut(
+            "deal_damage",
+            |_, this, (player, count): (String, usize)| {
+                let player = this.player(&player)?;
+                this.game.deal_damage(player, count, false, this.events);
+                Ok(())
+            },
+        );
+    }
+}
@@ -0,0 +1,634 @@
+//! Lua scripting host for cards whose effects can't be expressed by the effect parser.
+//!
+//! A card's script lives at `<root>/<set>/<card id>.lua`, e.g. `scripts/OP01/OP01-001.lua`, and
+//! registers handlers for the timings it cares about:
+//!
+//! ```lua
+//! register("on_play", function(game)
+//!     game:draw(1)
+//! end)
+//! ```
+//!
+//! Scripts run in a sandbox that only exposes their own copy of the `string`, `table` and `math`
+//! libraries plus the `game` object passed to each handler (see [`api`] for the methods it
+//! provides), and are stopped once they run too many instructions. Scripts are loaded lazily and
+//! can be reloaded from disk with [`ScriptHost::reload_changed`].
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+
+use data::{effect::Timing, CardId};
+use mlua::{Function, HookTriggers, Lua, LuaOptions, RegistryKey, StdLib, Table};
+use thiserror::Error;
+
+use crate::game::{GameEvent, GameState, InstanceId};
+
+pub mod api;
+
+/// Upper bound on the memory all scripts together may allocate
+const MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Number of instructions a script may run each time it is loaded or one of its handlers runs
+const INSTRUCTION_BUDGET: u32 = 10_000_000;
+
+/// Number of instructions between two checks of the instruction budget
+const HOOK_INTERVAL: u32 = 1000;
+
+/// Globals copied into each script's environment. `pcall` and `xpcall` are left out, as they
+/// would let a script catch the error that stops it once its instruction budget is spent.
+const SAFE_GLOBALS: &[&str] = &[
+    "assert", "error", "ipairs", "next", "pairs", "select", "tonumber", "tostring", "type",
+];
+
+/// Libraries each script receives its own copy of, so that changing them doesn't affect other
+/// scripts
+const LIBRARIES: &[&str] = &["string", "table", "math"];
+
+#[derive(Error, Debug)]
+pub enum ScriptError {
+    #[error("Failed to read script {0}: {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+
+    #[error(transparent)]
+    Lua(#[from] mlua::Error),
+
+    #[error("{card} registers a handler for unknown timing '{name}'")]
+    UnknownTiming { card: CardId, name: String },
+
+    #[error("No card with instance {0:?} exists")]
+    UnknownInstance(InstanceId),
+}
+
+/// Converts a handler name used by scripts into the timing it handles
+pub fn timing_from_handler_name(name: &str) -> Option<Timing> {
+    match name {
+        "on_play" => Some(Timing::OnPlay),
+        "when_attacking" => Some(Timing::WhenAttacking),
+        "activate_main" => Some(Timing::ActivateMain),
+        "main" => Some(Timing::Main),
+        "counter" => Some(Timing::Counter),
+        "trigger" => Some(Timing::Trigger),
+        "on_ko" => Some(Timing::OnKo),
+        "on_block" => Some(Timing::OnBlock),
+        "on_opponents_attack" => Some(Timing::OnOpponentsAttack),
+        "end_of_your_turn" => Some(Timing::EndOfYourTurn),
+        _ => None,
+    }
+}
+
+struct LoadedScript {
+    modified: Option<SystemTime>,
+    handlers: HashMap<Timing, RegistryKey>,
+}
+
+pub struct ScriptHost {
+    lua: Lua,
+    root: PathBuf,
+    /// Budget checks left before the running script is stopped
+    budget: Arc<AtomicU32>,
+    /// `None` records that a card has no script, so the file system isn't checked every time
+    scripts: HashMap<CardId, Option<LoadedScript>>,
+}
+
+impl ScriptHost {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, ScriptError> {
+        let lua = Lua::new_with(
+            StdLib::STRING | StdLib::TABLE | StdLib::MATH,
+            LuaOptions::default(),
+        )?;
+        lua.set_memory_limit(MEMORY_LIMIT)?;
+
+        let budget = Arc::new(AtomicU32::new(0));
+        let remaining = budget.clone();
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(HOOK_INTERVAL),
+            move |_, _| match remaining
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            {
+                Ok(_) => Ok(()),
+                Err(_) => Err(mlua::Error::runtime(
+                    "script exceeded its instruction budget",
+                )),
+            },
+        );
+
+        Ok(Self {
+            lua,
+            root: root.into(),
+            budget,
+            scripts: HashMap::new(),
+        })
+    }
+
+    fn reset_budget(&self) {
+        self.budget
+            .store(INSTRUCTION_BUDGET / HOOK_INTERVAL, Ordering::Relaxed);
+    }
+
+    pub fn script_path(&self, card: CardId) -> PathBuf {
+        self.root
+            .join(card.set.to_string())
+            .join(format!("{card}.lua"))
+    }
+
+    /// Whether the card has a script registering a handler for the given timing
+    pub fn has_handler(&mut self, card: CardId, timing: Timing) -> Result<bool, ScriptError> {
+        Ok(self
+            .script(card)?
+            .is_some_and(|script| script.handlers.contains_key(&timing)))
+    }
+
+    /// Runs the handler of the card `source` for the given timing. Returns `Ok(None)` when the
+    /// card has no such handler, otherwise the events caused by the script.
+    pub fn run(
+        &mut self,
+        game: &mut GameState,
+        timing: Timing,
+        source: InstanceId,
+    ) -> Result<Option<Vec<GameEvent>>, ScriptError> {
+        let (controller, card) = game
+            .find_card(source)
+            .map(|(player, card)| (player, card.data.id))
+            .ok_or(ScriptError::UnknownInstance(source))?;
+
+        if self.script(card)?.is_none() {
+            return Ok(None);
+        }
+
+        let Some(key) = self.scripts[&card]
+            .as_ref()
+            .and_then(|script| script.handlers.get(&timing))
+        else {
+            return Ok(None);
+        };
+
+        let handler: Function = self.lua.registry_value(key)?;
+        let mut events = vec![];
+        self.reset_budget();
+        self.lua.scope(|scope| {
+            let api = api::ScriptApi::new(game, controller, source, &mut events);
+            let api = scope.create_nonstatic_userdata(api)?;
+            handler.call::<_, ()>(api)
+        })?;
+
+        Ok(Some(events))
+    }
+
+    /// Runs the handlers that the given events cause: `on_play` for played cards,
+    /// `when_attacking` for attackers, `on_block` for blockers, `on_ko` for K.O.'d Characters and
+    /// `trigger` for Life cards with a trigger. Events produced by handlers are dispatched as well,
+    /// and all of them are returned.
+    pub fn dispatch(
+        &mut self,
+        game: &mut GameState,
+        events: &[GameEvent],
+    ) -> Result<Vec<GameEvent>, ScriptError> {
+        let mut produced = vec![];
+
+        for event in events {
+            let (timing, source) = match *event {
+                GameEvent::CardPlayed { card, .. } => (Timing::OnPlay, card),
+                GameEvent::AttackDeclared { attacker, .. } => (Timing::WhenAttacking, attacker),
+                GameEvent::Blocked { blocker } => (Timing::OnBlock, blocker),
+                GameEvent::KnockedOut { card, .. } => (Timing::OnKo, card),
+                GameEvent::LifeLost {
+                    card,
+                    trigger: true,
+                    ..
+                } => (Timing::Trigger, card),
+                _ => continue,
+            };
+
+            if let Some(new_events) = self.run(game, timing, source)? {
+                let nested = self.dispatch(game, &new_events)?;
+                produced.extend(new_events);
+                produced.extend(nested);
+            }
+        }
+
+        Ok(produced)
+    }
+
+    /// Reloads every loaded script whose file changed on disk and forgets cards that had no script
+    /// so newly created files are picked up. Returns the cards that were reloaded.
+    pub fn reload_changed(&mut self) -> Result<Vec<CardId>, ScriptError> {
+        self.scripts.retain(|_, script| script.is_some());
+
+        let changed: Vec<CardId> = self
+            .scripts
+            .iter()
+            .filter(|(card, script)| {
+                let modified = modified_time(&self.script_path(**card));
+                script.as_ref().map(|script| script.modified) != Some(modified)
+            })
+            .map(|(card, _)| *card)
+            .collect();
+
+        for card in changed.iter() {
+            self.unload(*card)?;
+            self.script(*card)?;
+        }
+
+        Ok(changed)
+    }
+
+    fn unload(&mut self, card: CardId) -> Result<(), ScriptError> {
+        if let Some(Some(script)) = self.scripts.remove(&card) {
+            for (_, key) in script.handlers {
+                self.lua.remove_registry_value(key)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn script(&mut self, card: CardId) -> Result<Option<&LoadedScript>, ScriptError> {
+        if !self.scripts.contains_key(&card) {
+            let script = self.load(card)?;
+            self.scripts.insert(card, script);
+        }
+
+        Ok(self.scripts[&card].as_ref())
+    }
+
+    fn load(&self, card: CardId) -> Result<Option<LoadedScript>, ScriptError> {
+        let path = self.script_path(card);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let modified = modified_time(&path);
+        let source =
+            std::fs::read_to_string(&path).map_err(|e| ScriptError::Io(path.clone(), e))?;
+
+        let handlers_table = self.lua.create_table()?;
+        let env = self.sandbox_env(&handlers_table)?;
+        self.reset_budget();
+        self.lua
+            .load(&source)
+            .set_name(path.to_string_lossy())
+            .set_environment(env)
+            .exec()?;
+
+        let mut handlers = HashMap::new();
+        for pair in handlers_table.pairs::<String, Function>() {
+            let (name, handler) = pair?;
+            let timing = timing_from_handler_name(&name)
+                .ok_or_else(|| ScriptError::UnknownTiming { card, name })?;
+            handlers.insert(timing, self.lua.create_registry_value(handler)?);
+        }
+
+        Ok(Some(LoadedScript { modified, handlers }))
+    }
+
+    /// Builds the global environment for one script. `register(timing, handler)` stores into
+    /// `handlers`.
+    fn sandbox_env<'lua>(&'lua self, handlers: &Table<'lua>) -> Result<Table<'lua>, ScriptError> {
+        let globals = self.lua.globals();
+        let env = self.lua.create_table()?;
+        for name in SAFE_GLOBALS {
+            env.set(*name, globals.get::<_, mlua::Value>(*name)?)?;
+        }
+
+        for name in LIBRARIES {
+            let library: Table = globals.get(*name)?;
+            let copy = self.lua.create_table()?;
+            for pair in library.pairs::<mlua::Value, mlua::Value>() {
+                let (key, value) = pair?;
+                copy.set(key, value)?;
+            }
+            env.set(*name, copy)?;
+        }
+
+        let register: Function = self
+            .lua
+            .load(
+                "local handlers = ...
+                return function(timing, handler)
+                    assert(type(timing) == 'string', 'timing must be a string')
+                    assert(type(handler) == 'function', 'handler must be a function')
+                    handlers[timing] = handler
+                end",
+            )
+            .set_name("register")
+            .call(handlers.clone())?;
+        env.set("register", register)?;
+
+        Ok(env)
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc, time::Duration};
+
+    use data::CardData;
+
+    use super::*;
+    use crate::game::{Card, FieldCard, PlayerId, PlayerSetup};
+
+    /// A fresh script directory holding the given `(card id, source)` scripts
+    fn scripts(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("runtime-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for (card, source) in files {
+            write_script(&root, card, source);
+        }
+        root
+    }
+
+    fn write_script(root: &Path, card: &str, source: &str) {
+        let card = CardId::from_str(card).unwrap();
+        let dir = root.join(card.set.to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(format!("{card}.lua")), source).unwrap();
+    }
+
+    fn card(id: &str, ty: &str) -> Arc<CardData> {
+        Arc::new(
+            serde_json::from_str(&format!(
+                r#"{{"id":"{id}","release_set":"ST01","rarity":"C","ty":"{ty}","name":"{id}","image_name":"{id}.png","cost_life":1,"power":3000,"counter":null,"color":["Red"],"effect":null,"trigger":null,"subtype":[],"attribute":[]}}"#
+            ))
+            .unwrap(),
+        )
+    }
+
+    fn id(card: &str) -> CardId {
+        CardId::from_str(card).unwrap()
+    }
+
+    /// A game between two ST01-001 Leaders with decks of ST01-002
+    fn game() -> GameState {
+        let setup = || PlayerSetup {
+            leader: card("ST01-001", "LEADER"),
+            deck: (0..10).map(|_| card("ST01-002", "CHARACTER")).collect(),
+        };
+        GameState::new([setup(), setup()], 0).unwrap()
+    }
+
+    fn add_to_field(game: &mut GameState, instance: u32, card_id: &str) -> InstanceId {
+        let instance = InstanceId(instance);
+        game.player_mut(PlayerId::First)
+            .characters
+            .push(FieldCard::new(Card::new(
+                instance,
+                card(card_id, "CHARACTER"),
+            )));
+        instance
+    }
+
+    #[test]
+    fn runs_registered_handlers() {
+        let root = scripts(
+            "run",
+            &[(
+                "ST01-001",
+                r#"register("on_play", function(game) game:draw(1) end)"#,
+            )],
+        );
+        let mut host = ScriptHost::new(&root).unwrap();
+        let mut game = game();
+        let leader = game.player(PlayerId::First).leader.instance();
+
+        assert!(host.has_handler(id("ST01-001"), Timing::OnPlay).unwrap());
+        assert!(!host.has_handler(id("ST01-001"), Timing::Counter).unwrap());
+        assert!(!host.has_handler(id("ST01-002"), Timing::OnPlay).unwrap());
+
+        let events = host
+            .run(&mut game, Timing::OnPlay, leader)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            events[..],
+            [GameEvent::CardDrawn {
+                player: PlayerId::First,
+                ..
+            }]
+        ));
+        assert!(host
+            .run(&mut game, Timing::Counter, leader)
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            host.run(&mut game, Timing::OnPlay, InstanceId(999)),
+            Err(ScriptError::UnknownInstance(InstanceId(999)))
+        ));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_timings() {
+        let root = scripts(
+            "timing",
+            &[("ST01-001", r#"register("on_attack", function(game) end)"#)],
+        );
+        let mut host = ScriptHost::new(&root).unwrap();
+
+        match host.has_handler(id("ST01-001"), Timing::OnPlay) {
+            Err(ScriptError::UnknownTiming { card, name }) => {
+                assert_eq!(card, id("ST01-001"));
+                assert_eq!(name, "on_attack");
+            }
+            other => panic!("expected an unknown timing, got {other:?}"),
+        }
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn sandbox_hides_unsafe_globals() {
+        let root = scripts(
+            "sandbox",
+            &[(
+                "ST01-001",
+                r#"
+                for _, name in ipairs({"os", "io", "require", "load", "dofile", "print", "debug", "pcall", "xpcall"}) do
+                    assert(_ENV[name] == nil, name .. " is available")
+                end
+                register("on_play", function(game) end)
+                "#,
+            )],
+        );
+        let mut host = ScriptHost::new(&root).unwrap();
+
+        assert!(host.has_handler(id("ST01-001"), Timing::OnPlay).unwrap());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn scripts_cannot_change_each_others_libraries() {
+        let root = scripts(
+            "libraries",
+            &[
+                (
+                    "ST01-001",
+                    "string.format = nil\nmath.random = nil\ntable.insert = nil",
+                ),
+                (
+                    "ST01-002",
+                    r#"
+                    assert(string.format and math.random and table.insert)
+                    assert(("luffy"):upper() == "LUFFY")
+                    register("on_play", function(game) end)
+                    "#,
+                ),
+            ],
+        );
+        let mut host = ScriptHost::new(&root).unwrap();
+
+        assert!(!host.has_handler(id("ST01-001"), Timing::OnPlay).unwrap());
+        assert!(host.has_handler(id("ST01-002"), Timing::OnPlay).unwrap());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn stops_scripts_that_run_too_long() {
+        let root = scripts(
+            "budget",
+            &[
+                (
+                    "ST01-001",
+                    r#"
+                    register("on_play", function(game) while true do end end)
+                    register("counter", function(game) game:draw(1) end)
+                    "#,
+                ),
+                ("ST01-002", "while true do end"),
+            ],
+        );
+        let mut host = ScriptHost::new(&root).unwrap();
+        let mut game = game();
+        let leader = game.player(PlayerId::First).leader.instance();
+
+        let error = host.run(&mut game, Timing::OnPlay, leader).unwrap_err();
+        assert!(error.to_string().contains("instruction budget"), "{error}");
+
+        // The budget is reset for the next handler
+        assert!(host.run(&mut game, Timing::Counter, leader).is_ok());
+
+        let error = host
+            .has_handler(id("ST01-002"), Timing::OnPlay)
+            .unwrap_err();
+        assert!(error.to_string().contains("instruction budget"), "{error}");
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn scripts_cannot_catch_the_budget_error() {
+        let root = scripts(
+            "budget-pcall",
+            &[(
+                "ST01-001",
+                r#"
+                register("on_play", function(game)
+                    while true do
+                        pcall(function() while true do end end)
+                    end
+                end)
+                "#,
+            )],
+        );
+        let mut host = ScriptHost::new(&root).unwrap();
+        let mut game = game();
+        let leader = game.player(PlayerId::First).leader.instance();
+
+        assert!(host.run(&mut game, Timing::OnPlay, leader).is_err());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn reloads_changed_scripts() {
+        let root = scripts(
+            "reload",
+            &[("ST01-001", r#"register("on_play", function(game) end)"#)],
+        );
+        let mut host = ScriptHost::new(&root).unwrap();
+
+        assert!(!host.has_handler(id("ST01-001"), Timing::Counter).unwrap());
+        assert!(!host.has_handler(id("ST01-002"), Timing::OnPlay).unwrap());
+        assert!(host.reload_changed().unwrap().is_empty());
+
+        write_script(
+            &root,
+            "ST01-001",
+            r#"register("counter", function(game) end)"#,
+        );
+        write_script(
+            &root,
+            "ST01-002",
+            r#"register("on_play", function(game) end)"#,
+        );
+        // Make sure the change is visible even on file systems with coarse timestamps
+        let path = host.script_path(id("ST01-001"));
+        let modified = modified_time(&path).unwrap() + Duration::from_secs(1);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        assert_eq!(host.reload_changed().unwrap(), vec![id("ST01-001")]);
+        assert!(host.has_handler(id("ST01-001"), Timing::Counter).unwrap());
+        assert!(!host.has_handler(id("ST01-001"), Timing::OnPlay).unwrap());
+        assert!(host.has_handler(id("ST01-002"), Timing::OnPlay).unwrap());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn dispatch_runs_handlers_of_produced_events() {
+        let root = scripts(
+            "dispatch",
+            &[
+                (
+                    "ST01-003",
+                    r#"register("on_play", function(game) game:ko(101) end)"#,
+                ),
+                (
+                    "ST01-004",
+                    r#"register("on_ko", function(game) game:draw(1) end)"#,
+                ),
+            ],
+        );
+        let mut host = ScriptHost::new(&root).unwrap();
+        let mut game = game();
+        let played = add_to_field(&mut game, 100, "ST01-003");
+        let knocked_out = add_to_field(&mut game, 101, "ST01-004");
+
+        let events = host
+            .dispatch(
+                &mut game,
+                &[GameEvent::CardPlayed {
+                    player: PlayerId::First,
+                    card: played,
+                }],
+            )
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0],
+            GameEvent::KnockedOut {
+                player: PlayerId::First,
+                card: knocked_out,
+            }
+        );
+        assert!(matches!(
+            events[1],
+            GameEvent::CardDrawn {
+                player: PlayerId::First,
+                ..
+            }
+        ));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
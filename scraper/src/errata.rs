@@ -0,0 +1,350 @@
+//! Errata from the official errata page, which the card list links to from each affected card.
+//! Every erratum names the card and gives its text before and after the change, and each section
+//! of the page, linked to as `#errata_<n>`, says when its errata came into effect.
+
+use std::{collections::BTreeSet, path::Path, str::FromStr};
+
+use data::{CardData, CardId, TextVersion};
+use scraper::{ElementRef, Html};
+
+use crate::{error::ScrapeError, scrape::download};
+
+/// Errata pages live under this path, the other notes link to announcements and ban lists
+const ERRATA_PAGE: &str = "/rules/errata_card/";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Erratum {
+    pub id: CardId,
+    /// The id of the section on the page, which is what the card list links to
+    pub anchor: String,
+    pub before: String,
+    pub after: String,
+    pub effective: Option<String>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Part {
+    Before,
+    After,
+}
+
+/// The part of an erratum a heading introduces, e.g. "Before" or "After:"
+fn part_label(text: &str) -> Option<Part> {
+    let label = text.trim().trim_end_matches(':').trim().to_lowercase();
+    let label = label.strip_suffix(" text").unwrap_or(&label);
+    match label {
+        "before" | "before correction" | "incorrect" | "old" | "printed" => Some(Part::Before),
+        "after" | "after correction" | "correct" | "corrected" | "new" | "revised" => {
+            Some(Part::After)
+        }
+        _ => None,
+    }
+}
+
+/// The date in a line like "Effective date: 2024/03/08"
+fn effective_date(text: &str) -> Option<String> {
+    let lower = text.to_lowercase();
+    if !(lower.contains("effective") || lower.contains("enforcement")) {
+        return None;
+    }
+
+    let date = text.split_once(':').map_or(text, |(_, date)| date).trim();
+    (!date.is_empty()).then(|| date.to_string())
+}
+
+/// The card id an erratum starts with, e.g. `OP01-013` in "OP01-013 Sanji"
+fn leading_card_id(text: &str) -> Option<CardId> {
+    let token = text.split_whitespace().next()?;
+    token
+        .contains('-')
+        .then(|| CardId::from_str(token.trim_end_matches(':')).ok())
+        .flatten()
+}
+
+fn is_section(el: ElementRef) -> bool {
+    el.attr("id").is_some_and(|id| {
+        id.starts_with("errata_") || (id.contains('-') && CardId::from_str(id).is_ok())
+    })
+}
+
+fn parse_section(section: ElementRef, anchor: &str) -> Vec<Erratum> {
+    let mut errata = vec![];
+    let mut effective = None;
+    let mut current: Option<(Erratum, Option<Part>)> = None;
+
+    for text in section
+        .text()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+    {
+        if let Some(date) = effective_date(text) {
+            effective = Some(date);
+        } else if let Some(id) = leading_card_id(text) {
+            // The card id is also repeated next to its image and name
+            if current.as_ref().is_some_and(|(erratum, part)| {
+                erratum.id == id && part.is_none() && erratum.before.is_empty()
+            }) {
+                continue;
+            }
+
+            errata.extend(current.take().map(|(erratum, _)| erratum));
+            current = Some((
+                Erratum {
+                    id,
+                    anchor: anchor.to_string(),
+                    before: String::new(),
+                    after: String::new(),
+                    effective: None,
+                },
+                None,
+            ));
+        } else if let Some(label) = part_label(text) {
+            if let Some((_, part)) = current.as_mut() {
+                *part = Some(label);
+            }
+        } else if let Some((erratum, Some(part))) = current.as_mut() {
+            let target = match part {
+                Part::Before => &mut erratum.before,
+                Part::After => &mut erratum.after,
+            };
+            if !target.is_empty() {
+                target.push('\n');
+            }
+            target.push_str(text);
+        }
+    }
+
+    errata.extend(current.map(|(erratum, _)| erratum));
+    errata
+        .into_iter()
+        .filter(|erratum| !erratum.before.is_empty() && !erratum.after.is_empty())
+        .map(|erratum| Erratum {
+            effective: effective.clone(),
+            ..erratum
+        })
+        .collect()
+}
+
+/// Parses every erratum on an errata page
+pub fn parse_errata_page(html: &Html) -> Vec<Erratum> {
+    html.root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|el| is_section(*el))
+        .flat_map(|section| parse_section(section, section.attr("id").unwrap()))
+        .collect()
+}
+
+/// Downloads the errata pages linked from the cards, unless they are cached already, and parses
+/// them. Pages that cannot be fetched are reported in `errors`.
+pub fn fetch_errata(
+    tld: &str,
+    path: &Path,
+    cards: &[CardData],
+    errors: &mut Vec<ScrapeError>,
+) -> Vec<Erratum> {
+    let pages: BTreeSet<&str> = cards
+        .iter()
+        .flat_map(|card| card.errata.iter())
+        .filter_map(|link| link.split('#').next())
+        .filter(|page| page.starts_with(ERRATA_PAGE))
+        .collect();
+
+    let mut errata = vec![];
+    for page in pages {
+        let cached = path.join(format!(
+            "html/{}.html",
+            page.trim_matches('/').replace('/', "_")
+        ));
+        if !cached.exists() {
+            let url = format!("https://{tld}.onepiece-cardgame.com{page}");
+            if let Err(e) = download(&url, &cached) {
+                errors.push(e);
+                continue;
+            }
+        }
+
+        let html = match std::fs::read_to_string(&cached) {
+            Ok(html) => html,
+            Err(e) => {
+                errors.push(ScrapeError::Read(cached, e));
+                continue;
+            }
+        };
+        errata.extend(parse_errata_page(&Html::parse_document(&html)));
+        println!("Parsed {}", cached.display());
+    }
+
+    errata
+}
+
+/// The text history of a card from the errata its notes link to, oldest first. The card list
+/// already shows the current text, which is kept as the last version.
+pub fn text_history(card: &CardData, errata: &[Erratum]) -> Vec<TextVersion> {
+    let mut history = vec![];
+    for link in card.errata.iter() {
+        let Some((_, anchor)) = link.split_once('#') else {
+            continue;
+        };
+        let Some(erratum) = errata
+            .iter()
+            .find(|erratum| erratum.id == card.id && erratum.anchor == anchor)
+        else {
+            continue;
+        };
+
+        if history.is_empty() {
+            history.push(TextVersion {
+                effect: Some(erratum.before.clone()),
+                effective: None,
+                errata: None,
+            });
+        }
+        history.push(TextVersion {
+            effect: Some(erratum.after.clone()),
+            effective: erratum.effective.clone(),
+            errata: Some(link.clone()),
+        });
+    }
+
+    if let Some(current) = history.last_mut() {
+        current.effect = card.effect.clone();
+    }
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KID: &str = "OP01-051";
+
+    fn fixture() -> Vec<Erratum> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden/errata_card.html");
+        let html = std::fs::read_to_string(path).unwrap();
+        parse_errata_page(&Html::parse_document(&html))
+    }
+
+    fn id(id: &str) -> CardId {
+        CardId::from_str(id).unwrap()
+    }
+
+    #[test]
+    fn labels_and_card_ids() {
+        assert!(part_label("Before") == Some(Part::Before));
+        assert!(part_label(" After correction: ") == Some(Part::After));
+        assert!(part_label("Before text") == Some(Part::Before));
+        assert!(part_label("Before the change").is_none());
+
+        assert_eq!(
+            leading_card_id("OP01-051 Eustass\"Captain\"Kid"),
+            Some(id(KID))
+        );
+        assert_eq!(leading_card_id("ST04-001: Kaido"), Some(id("ST04-001")));
+        assert_eq!(leading_card_id("[Activate: Main] DON!! −7"), None);
+
+        assert_eq!(
+            effective_date("Effective date: 2023/01/27").as_deref(),
+            Some("2023/01/27")
+        );
+        assert_eq!(effective_date("Errata for OP01"), None);
+    }
+
+    #[test]
+    fn parses_every_erratum_of_a_section() {
+        let errata = fixture();
+        let ids: Vec<_> = errata
+            .iter()
+            .map(|erratum| (erratum.id.to_string(), erratum.anchor.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                (KID.to_string(), "errata_04"),
+                ("ST04-001".to_string(), "errata_04"),
+                (KID.to_string(), "errata_05"),
+            ]
+        );
+
+        let kaido = &errata[1];
+        assert_eq!(
+            kaido.before,
+            "[Activate: Main] [Once Per Turn] DON!! −7: Trash up to 1 of your opponent's Life cards."
+        );
+        assert!(kaido
+            .after
+            .starts_with("[Activate: Main] [Once Per Turn] DON!! −7 (You may"));
+    }
+
+    #[test]
+    fn skips_the_card_id_next_to_the_image() {
+        let kid = &fixture()[0];
+        assert_eq!(kid.id, id(KID));
+        assert_eq!(
+            kid.before,
+            "[DON!! x1] [Opponent's Turn] If this Character is rested, your opponent cannot attack \
+             any card other than this Character.\n[Activate: Main] [Once Per Turn] You may rest \
+             this Character: Play up to 1 Character card with a cost of 3 or less from your hand."
+        );
+        assert!(kid.after.ends_with("from your hand and rest it."));
+        assert!(!kid.after.contains(KID));
+    }
+
+    #[test]
+    fn every_erratum_gets_the_date_of_its_section() {
+        let effective: Vec<_> = fixture()
+            .into_iter()
+            .map(|erratum| erratum.effective)
+            .collect();
+        assert_eq!(
+            effective,
+            [
+                Some("2023/01/27".to_string()),
+                Some("2023/01/27".to_string()),
+                Some("2023/06/30".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn history_of_a_card_linking_two_sections() {
+        let errata = fixture();
+        let card: CardData = serde_json::from_str(
+            r#"{"id":"OP01-051","release_set":"OP01","rarity":"SR","ty":"CHARACTER","name":"Eustass\"Captain\"Kid","image_name":"OP01-051.png","cost_life":8,"power":8000,"counter":null,"color":["Green"],"effect":"[DON!! x1] [Opponent's Turn] If this Character is rested, your opponent cannot attack any card other than the Character [Eustass\"Captain\"Kid].\n[Activate: Main] [Once Per Turn] You may rest this Character: Play up to 1 Character card with a cost of 3 or less from your hand.","trigger":null,"subtype":["Supernovas","Kid Pirates"],"attribute":["Special"],"products":[{"name":"ROMANCE DAWN","set":"OP01"}],"errata":["/rules/errata_card/#errata_04","/rules/errata_card/#errata_05"]}"#,
+        )
+        .unwrap();
+
+        let history = text_history(&card, &errata);
+        assert_eq!(
+            history,
+            [
+                TextVersion {
+                    effect: Some(errata[0].before.clone()),
+                    effective: None,
+                    errata: None,
+                },
+                TextVersion {
+                    effect: Some(errata[0].after.clone()),
+                    effective: Some("2023/01/27".to_string()),
+                    errata: Some("/rules/errata_card/#errata_04".to_string()),
+                },
+                TextVersion {
+                    effect: card.effect.clone(),
+                    effective: Some("2023/06/30".to_string()),
+                    errata: Some("/rules/errata_card/#errata_05".to_string()),
+                },
+            ]
+        );
+
+        // Links to sections that are not on the page, or that are about other cards, are ignored
+        let kaido = CardData {
+            id: id("ST04-001"),
+            errata: vec![
+                "/rules/errata_card/#errata_01".to_string(),
+                "/rules/errata_card/#errata_05".to_string(),
+            ],
+            ..card
+        };
+        assert!(text_history(&kaido, &errata).is_empty());
+    }
+}
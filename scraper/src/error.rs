@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use scraper::ElementRef;
+use thiserror::Error;
+
+/// What is wrong with a field of a card
+#[derive(Error, Debug)]
+pub enum FieldError {
+    #[error("is missing")]
+    Missing,
+
+    #[error("has the invalid value '{value}': {reason}")]
+    Invalid { value: String, reason: String },
+}
+
+/// A card that could not be parsed because of one of its fields
+#[derive(Error, Debug)]
+#[error("{field} {error}")]
+pub struct CardError {
+    pub field: &'static str,
+    pub error: FieldError,
+    /// The HTML of the field, or of the element that should contain it when it is missing
+    pub html: String,
+}
+
+impl CardError {
+    pub fn missing(field: &'static str, el: ElementRef) -> Self {
+        Self {
+            field,
+            error: FieldError::Missing,
+            html: el.html(),
+        }
+    }
+
+    pub fn invalid(
+        field: &'static str,
+        value: &str,
+        reason: impl ToString,
+        el: ElementRef,
+    ) -> Self {
+        Self {
+            field,
+            error: FieldError::Invalid {
+                value: value.to_string(),
+                reason: reason.to_string(),
+            },
+            html: el.html(),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ScrapeError {
+    #[error("Failed to fetch {url}: {source}")]
+    Fetch {
+        url: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[error("Failed to read {0}: {1}")]
+    Read(PathBuf, #[source] std::io::Error),
+
+    #[error("Failed to write {0}: {1}")]
+    Write(PathBuf, #[source] std::io::Error),
+
+    #[error("Invalid series overrides {0}: {1}")]
+    InvalidOverrides(PathBuf, #[source] serde_json::Error),
+
+    #[error("No series found in the card list index {0}")]
+    NoSeries(PathBuf),
+
+    #[error("Series {series}: could not find {element} in the card list")]
+    Page { series: u32, element: &'static str },
+
+    #[error("Series {series}, card {index}: {error}")]
+    Card {
+        series: u32,
+        /// Position of the card in the series' card list
+        index: usize,
+        #[source]
+        error: CardError,
+    },
+}
+
+impl ScrapeError {
+    /// The HTML the error was found in, for the report
+    pub fn html(&self) -> Option<&str> {
+        match self {
+            Self::Card { error, .. } => Some(&error.html),
+            _ => None,
+        }
+    }
+}
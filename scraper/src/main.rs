@@ -1,5 +1,12 @@
+mod errata;
+mod error;
+mod parse;
 mod scrape;
+mod series;
 
 fn main() {
-    scrape::scrape();
+    let errors = scrape::scrape();
+    if !errors.is_empty() {
+        std::process::exit(1);
+    }
 }
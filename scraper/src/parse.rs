@@ -0,0 +1,520 @@
+//! Parsing of the card list pages, kept apart from fetching them so stored pages can be parsed
+//! offline.
+
+use data::{Attribute, CardData, CardType, Color, Product, SetId, Subtype};
+use scraper::{ElementRef, Html};
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use crate::{
+    error::{CardError, ScrapeError},
+    series::Series,
+};
+
+fn find_cardlist_element(dom: ElementRef) -> Option<ElementRef> {
+    dom.child_elements()
+        .find(|el| Some("cardlist") == el.attr("id"))
+}
+
+fn find_maincol(el: ElementRef) -> Option<ElementRef> {
+    let mut found = None;
+    for node in el.child_elements() {
+        if Some("mainCol") == node.attr("class") {
+            found = Some(node);
+        }
+    }
+
+    found?
+        .child_elements()
+        .find(|node| node.value().name() == "article")
+}
+
+fn find_resultcol(el: ElementRef) -> Option<ElementRef> {
+    let mut found = None;
+    for node in el.child_elements() {
+        if Some("contentsWrap isIndex") == node.attr("class") {
+            found = Some(node);
+        }
+    }
+
+    found?
+        .child_elements()
+        .find(|node| Some("resultCol") == node.attr("class"))
+}
+
+fn gather_cards(el: ElementRef) -> Vec<ElementRef> {
+    let mut cards = vec![];
+
+    for node in el.child_elements() {
+        if node.value().name() == "dl" && Some("modalCol") == node.attr("class") {
+            cards.push(node);
+        }
+    }
+
+    cards
+}
+
+fn get_child_element_by_name<'a>(el: ElementRef<'a>, name: &str) -> Option<ElementRef<'a>> {
+    for node in el.child_elements() {
+        if node.value().name() == name {
+            return Some(node);
+        }
+    }
+
+    None
+}
+
+fn get_child_element_by_class<'a>(el: ElementRef<'a>, classes: &str) -> Option<ElementRef<'a>> {
+    for node in el.child_elements() {
+        if Some(classes) == node.attr("class") {
+            return Some(node);
+        }
+    }
+
+    None
+}
+
+fn collect_all_elements_with_name<'a>(el: ElementRef<'a>, name: &str) -> Vec<ElementRef<'a>> {
+    let mut out = vec![];
+    for node in el.child_elements() {
+        if node.value().name() == name {
+            out.push(node);
+        }
+    }
+
+    out
+}
+
+/// Parses the value of a field, reporting the element it came from if it is invalid
+fn parse_field<T>(field: &'static str, value: &str, el: ElementRef) -> Result<T, CardError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    T::from_str(value).map_err(|e| CardError::invalid(field, value, e, el))
+}
+
+/// Parses a number, where "-" stands for no value
+fn parse_number(
+    field: &'static str,
+    value: &str,
+    el: ElementRef,
+) -> Result<Option<usize>, CardError> {
+    if value == "-" {
+        Ok(None)
+    } else {
+        parse_field(field, value, el).map(Some)
+    }
+}
+
+/// Parses one `modalCol` of the card list
+pub fn card_data_from_el(el: ElementRef, set_id: SetId) -> Result<CardData, CardError> {
+    let dd = get_child_element_by_name(el, "dd").ok_or_else(|| CardError::missing("dd", el))?;
+    let front_col = get_child_element_by_class(dd, "frontCol")
+        .ok_or_else(|| CardError::missing("frontCol", dd))?;
+    let img = get_child_element_by_name(front_col, "img")
+        .ok_or_else(|| CardError::missing("image", front_col))?;
+
+    let src = img
+        .attr("data-src")
+        .ok_or_else(|| CardError::missing("image", img))?;
+    let local_url = src.split_once('?').map_or(src, |(first, _)| first);
+    let image_name = local_url
+        .rsplit_once('/')
+        .map(|(_, name)| name.to_string())
+        .ok_or_else(|| CardError::invalid("image", src, "not a path to an image", img))?;
+
+    let back_col = get_child_element_by_class(dd, "backCol")
+        .ok_or_else(|| CardError::missing("backCol", dd))?;
+    let divs = collect_all_elements_with_name(back_col, "div");
+
+    let mut cost_life = None;
+    let mut attribute = None;
+    let mut power = None;
+    let mut counter = None;
+    let mut colors = None;
+    let mut subtype = None;
+    let mut effect = None;
+    let mut trigger = None;
+    let mut products = vec![];
+    let mut errata = vec![];
+
+    for div in divs {
+        if Some("color") == div.attr("class") {
+            for item in div.text() {
+                if !item.contains("Color") {
+                    colors = Some(
+                        item.split('/')
+                            .map(|color| parse_field::<Color>("color", color, div))
+                            .collect::<Result<Vec<_>, _>>()?,
+                    );
+                    break;
+                }
+            }
+        } else if Some("feature") == div.attr("class") {
+            for item in div.text() {
+                if !item.contains("Type") {
+                    subtype = Some(
+                        item.split('/')
+                            .map(|subtype| parse_field::<Subtype>("subtype", subtype, div))
+                            .collect::<Result<Vec<_>, _>>()?,
+                    );
+                    break;
+                }
+            }
+        } else if Some("text") == div.attr("class") {
+            let mut card_effect = String::new();
+            for item in div.text() {
+                if item != "Effect" && item != "-" {
+                    if card_effect.is_empty() {
+                        card_effect = item.to_string();
+                    } else {
+                        card_effect.push('\n');
+                        card_effect.push_str(item);
+                    }
+                }
+            }
+
+            effect = Some((!card_effect.is_empty()).then_some(card_effect));
+        } else if Some("trigger") == div.attr("class") {
+            for item in div.text() {
+                if item != "Trigger" {
+                    trigger = Some((item != "-").then(|| item.to_string()));
+                    break;
+                }
+            }
+        } else if Some("getInfo") == div.attr("class") {
+            for item in div.text() {
+                if item != "Card Set(s)" && !item.trim().is_empty() {
+                    products.push(Product::parse(item));
+                }
+            }
+        } else if Some("getInfo remarks") == div.attr("class") {
+            // The notes link to errata, but also to ban lists and other announcements
+            for link in collect_all_elements_with_name(div, "a") {
+                let label = link.text().collect::<String>();
+                let Some(href) = link.attr("href") else {
+                    continue;
+                };
+
+                if href.to_lowercase().contains("errata") || label.to_lowercase().contains("errata")
+                {
+                    errata.push(href.to_string());
+                }
+            }
+        } else if Some("col2") == div.attr("class") {
+            let divs = collect_all_elements_with_name(div, "div");
+            for div in divs {
+                if Some("cost") == div.attr("class") {
+                    for item in div.text() {
+                        if !item.contains("Cost") && !item.contains("Life") {
+                            cost_life = Some(parse_number("cost", item, div)?.unwrap_or(0));
+                            break;
+                        }
+                    }
+                } else if Some("attribute") == div.attr("class") {
+                    for item in div.text() {
+                        let item = item.trim();
+                        if !item.contains("Attribute") && !item.is_empty() {
+                            attribute = Some(
+                                item.split('/')
+                                    .filter(|item| *item != "-")
+                                    .map(|a| parse_field::<Attribute>("attribute", a, div))
+                                    .collect::<Result<Vec<_>, _>>()?,
+                            );
+                            break;
+                        }
+                    }
+                } else if Some("power") == div.attr("class") {
+                    for item in div.text() {
+                        if !item.contains("Power") {
+                            power = Some(parse_number("power", item, div)?);
+                            break;
+                        }
+                    }
+                } else if Some("counter") == div.attr("class") {
+                    for item in div.text() {
+                        if !item.contains("Counter") {
+                            counter = Some(parse_number("counter", item, div)?);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let dt = get_child_element_by_name(el, "dt").ok_or_else(|| CardError::missing("dt", el))?;
+    let div = get_child_element_by_class(dt, "infoCol")
+        .ok_or_else(|| CardError::missing("infoCol", dt))?;
+
+    let spans = collect_all_elements_with_name(div, "span");
+    let span_text = |index: usize, field: &'static str| {
+        spans
+            .get(index)
+            .and_then(|span| span.text().next())
+            .map(str::trim)
+            .ok_or_else(|| CardError::missing(field, div))
+    };
+
+    let id = span_text(0, "id")?;
+    let rarity = span_text(1, "rarity")?;
+    let ty = span_text(2, "type")?;
+    let name = get_child_element_by_class(dt, "cardName")
+        .and_then(|name| name.text().next())
+        .ok_or_else(|| CardError::missing("name", dt))?
+        .trim()
+        .to_string();
+
+    println!("Parsing {}", id);
+
+    Ok(CardData {
+        id: parse_field("id", id, div)?,
+        release_set: set_id,
+        rarity: parse_field("rarity", rarity, div)?,
+        ty: parse_field::<CardType>("type", ty, div)?,
+        name,
+        image_name,
+        cost_life: cost_life.ok_or_else(|| CardError::missing("cost", back_col))?,
+        power: power.ok_or_else(|| CardError::missing("power", back_col))?,
+        counter: counter.ok_or_else(|| CardError::missing("counter", back_col))?,
+        color: colors.ok_or_else(|| CardError::missing("color", back_col))?,
+        effect: effect.ok_or_else(|| CardError::missing("effect", back_col))?,
+        trigger: trigger.flatten(),
+        subtype: subtype.ok_or_else(|| CardError::missing("subtype", back_col))?,
+        attribute: attribute.ok_or_else(|| CardError::missing("attribute", back_col))?,
+        printings: vec![],
+        products,
+        errata,
+        text_history: vec![],
+    })
+}
+
+/// Parses the series dropdown that every card list page has, which lists every series of the
+/// region
+pub fn parse_series_options(html: &str) -> Vec<Series> {
+    let html = Html::parse_document(html);
+    let Some(select) = html
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .find(|el| el.value().name() == "select" && Some("series") == el.attr("id"))
+    else {
+        return vec![];
+    };
+
+    // The first options are "Recording" and "ALL", which have no series id
+    collect_all_elements_with_name(select, "option")
+        .into_iter()
+        .filter_map(|option| {
+            let id = option.attr("value")?.parse::<u32>().ok()?;
+            Some(Series::parse(id, &option.text().collect::<String>()))
+        })
+        .collect()
+}
+
+/// Parses the cards of a series' card list. Cards that fail to parse are reported in `errors`
+/// without stopping the others from being parsed.
+pub fn parse_series(
+    html: &str,
+    series: u32,
+    set_id: SetId,
+    errors: &mut Vec<ScrapeError>,
+) -> Vec<CardData> {
+    let html = Html::parse_document(html);
+    let page_error = |element| ScrapeError::Page { series, element };
+
+    let resultcol = find_cardlist_element(html.root_element())
+        .ok_or_else(|| page_error("cardlist"))
+        .and_then(|cardlist| find_maincol(cardlist).ok_or_else(|| page_error("mainCol")))
+        .and_then(|maincol| find_resultcol(maincol).ok_or_else(|| page_error("resultCol")));
+    let resultcol = match resultcol {
+        Ok(resultcol) => resultcol,
+        Err(e) => {
+            errors.push(e);
+            return vec![];
+        }
+    };
+
+    let mut cards = vec![];
+    for (index, card) in gather_cards(resultcol).into_iter().enumerate() {
+        match card_data_from_el(card, set_id) {
+            Ok(card) => cards.push(card),
+            Err(error) => errors.push(ScrapeError::Card {
+                series,
+                index,
+                error,
+            }),
+        }
+    }
+
+    cards
+}
+
+/// Parses the stored card list of every series from `<path>/html`. Series that were not stored
+/// are skipped, they failed to download and were reported already.
+pub fn parse_pages(series: &[Series], path: &Path, errors: &mut Vec<ScrapeError>) -> Vec<CardData> {
+    let mut all_cards = vec![];
+    for Series { id, set, .. } in series {
+        let html_path = path.join(format!("html/{id}.html"));
+        if !html_path.exists() {
+            continue;
+        }
+
+        let html = match std::fs::read_to_string(&html_path) {
+            Ok(html) => html,
+            Err(e) => {
+                errors.push(ScrapeError::Read(html_path, e));
+                continue;
+            }
+        };
+        all_cards.extend(parse_series(&html, *id, *set, errors));
+        println!("Parsed html/{id}.html");
+    }
+
+    all_cards
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::series::ProductKind;
+
+    fn repo_root() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+    }
+
+    /// Parses a stored card list and compares the cards with the expected JSONL in
+    /// `scraper/golden`. Run the tests with `UPDATE_GOLDEN=1` to write the expected output after
+    /// an intended change to the parser.
+    fn check_golden(fixture: &str, series: u32, golden: &str) {
+        let html = std::fs::read_to_string(repo_root().join(fixture)).unwrap();
+        let set_id = parse_series_options(&html)
+            .into_iter()
+            .find(|option| option.id == series)
+            .unwrap_or_else(|| panic!("{fixture} does not list series {series}"))
+            .set;
+
+        let mut errors = vec![];
+        let cards = parse_series(&html, series, set_id, &mut errors);
+        assert!(errors.is_empty(), "{fixture} failed to parse: {errors:#?}");
+        assert!(!cards.is_empty(), "{fixture} has no cards");
+
+        let actual: String = cards
+            .iter()
+            .map(|card| serde_json::to_string(card).unwrap() + "\n")
+            .collect();
+
+        let golden = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("golden")
+            .join(golden);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(golden.parent().unwrap()).unwrap();
+            std::fs::write(&golden, &actual).unwrap();
+            return;
+        }
+
+        let expected = std::fs::read_to_string(&golden).unwrap_or_else(|e| {
+            panic!(
+                "Missing {}, run with UPDATE_GOLDEN=1: {e}",
+                golden.display()
+            )
+        });
+        for (line, (expected, actual)) in expected.lines().zip(actual.lines()).enumerate() {
+            assert_eq!(
+                expected,
+                actual,
+                "{fixture} differs from {} on line {}",
+                golden.display(),
+                line + 1
+            );
+        }
+        assert_eq!(
+            expected.lines().count(),
+            actual.lines().count(),
+            "{fixture} has a different number of cards than {}",
+            golden.display()
+        );
+    }
+
+    #[test]
+    fn starter_deck_pages() {
+        check_golden("st01.html", 569001, "st01.jsonl");
+        check_golden("st02.html", 569002, "st02.jsonl");
+    }
+
+    #[test]
+    fn booster_page() {
+        check_golden("test.html", 569107, "test.jsonl");
+    }
+
+    #[test]
+    fn cached_pages() {
+        let dir = repo_root().join("cache/en/html");
+        let mut checked = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let Some(series) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u32>().ok())
+            else {
+                continue;
+            };
+
+            check_golden(
+                &format!("cache/en/html/{series}.html"),
+                series,
+                &format!("en/{series}.jsonl"),
+            );
+            checked += 1;
+        }
+        assert!(checked > 0, "No stored pages in {}", dir.display());
+    }
+
+    #[test]
+    fn discovers_series() {
+        let html = std::fs::read_to_string(repo_root().join("st01.html")).unwrap();
+        let series = parse_series_options(&html);
+        assert_eq!(series.len(), 24);
+
+        let find = |id| series.iter().find(|series| series.id == id).unwrap();
+        assert_eq!(find(569201).set, SetId::Extra(1));
+        assert_eq!(find(569201).product.name, "MEMORIAL COLLECTION");
+        assert_eq!(find(569201).kind, ProductKind::ExtraBooster);
+        assert_eq!(find(569013).set, SetId::Starter(13));
+        assert_eq!(find(569013).kind, ProductKind::UltraDeck);
+        assert_eq!(find(569101).set, SetId::Booster(1));
+        assert_eq!(find(569901).set, SetId::Promo);
+        assert_eq!(find(569901).kind, ProductKind::Promotion);
+        assert_eq!(find(569801).kind, ProductKind::Other);
+
+        let jp = std::fs::read_to_string(repo_root().join("cache/jp/html/556001.html")).unwrap();
+        let jp = parse_series_options(&jp);
+        assert_eq!(
+            jp.iter().find(|series| series.id == 556108).unwrap().set,
+            SetId::Booster(8)
+        );
+        assert_eq!(
+            jp.iter().find(|series| series.id == 556301).unwrap().set,
+            SetId::PremiumBooster(1)
+        );
+    }
+
+    #[test]
+    fn reports_invalid_fields() {
+        let html = std::fs::read_to_string(repo_root().join("st01.html"))
+            .unwrap()
+            .replacen("<h3>Power</h3>5000", "<h3>Power</h3>5k", 1);
+
+        let mut errors = vec![];
+        let cards = parse_series(&html, 569001, SetId::Starter(1), &mut errors);
+        assert_eq!(cards.len(), 16);
+        assert!(matches!(
+            errors.as_slice(),
+            [ScrapeError::Card { series: 569001, index: 0, error }]
+                if error.field == "power" && error.html.contains("5k")
+        ));
+    }
+}
@@ -0,0 +1,236 @@
+//! The series of the card list, discovered from the series dropdown of the card list page. An
+//! override file fixes the series the page gets wrong, by region and series id, e.g.
+//!
+//! ```json
+//! {
+//!     "jp": { "556701": { "set": "P", "kind": "promotion" } },
+//!     "en": { "569999": { "skip": true } }
+//! }
+//! ```
+//!
+//! Overrides for series missing from the dropdown add them, as long as they give the set.
+
+use std::{collections::BTreeMap, path::Path};
+
+use data::{Product, SetId};
+use serde::Deserialize;
+
+use crate::error::ScrapeError;
+
+/// Overrides are looked up relative to the directory the scraper runs from
+pub const OVERRIDES_PATH: &str = "./scraper/series_overrides.json";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProductKind {
+    BoosterPack,
+    ExtraBooster,
+    PremiumBooster,
+    StarterDeck,
+    /// Written "ULTRA DECK" or "ULTIMATE DECK" depending on the region
+    UltraDeck,
+    Promotion,
+    /// Other products and promotional sets, like "Family Deck Set"
+    Other,
+}
+
+impl ProductKind {
+    /// The kind of product a series label starts with, e.g. "BOOSTER PACK"
+    pub fn from_label(label: &str) -> Self {
+        match label.trim().to_uppercase().as_str() {
+            "BOOSTER PACK" => Self::BoosterPack,
+            "EXTRA BOOSTER" => Self::ExtraBooster,
+            "PREMIUM BOOSTER" => Self::PremiumBooster,
+            "STARTER DECK" => Self::StarterDeck,
+            "ULTRA DECK" | "ULTIMATE DECK" => Self::UltraDeck,
+            "PROMOTION CARD" => Self::Promotion,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// One entry of the series dropdown, e.g.
+/// `EXTRA BOOSTER <br class="spInline">-MEMORIAL COLLECTION- [EB-01]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Series {
+    /// The id used in the card list URL, `?series=<id>`
+    pub id: u32,
+    /// The set the cards of the series are released in. Series without a set code are promotional
+    /// products.
+    pub set: SetId,
+    pub product: Product,
+    pub kind: ProductKind,
+}
+
+impl Series {
+    pub fn parse(id: u32, label: &str) -> Self {
+        // The kind and the product are separated by a line break on small screens
+        let (kind, name) = match label.split_once('<') {
+            Some((kind, rest)) => (kind, rest.split_once('>').map_or(rest, |(_, name)| name)),
+            None => (label, label),
+        };
+
+        let product = Product::parse(name);
+        Self {
+            id,
+            set: product.set.unwrap_or(SetId::Promo),
+            kind: ProductKind::from_label(kind),
+            product,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeriesOverride {
+    set: Option<SetId>,
+    name: Option<String>,
+    kind: Option<ProductKind>,
+    /// Leaves the series out of the scrape
+    #[serde(default)]
+    skip: bool,
+}
+
+/// Overrides of one region, keyed by series id
+pub type Overrides = BTreeMap<u32, SeriesOverride>;
+
+/// Loads the overrides of every region. A missing file means no overrides.
+pub fn load_overrides(path: &Path) -> Result<BTreeMap<String, Overrides>, ScrapeError> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|e| ScrapeError::InvalidOverrides(path.to_path_buf(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(ScrapeError::Read(path.to_path_buf(), e)),
+    }
+}
+
+/// Applies the overrides of a region to the series discovered for it
+pub fn apply_overrides(mut series: Vec<Series>, overrides: &Overrides) -> Vec<Series> {
+    for (id, fix) in overrides {
+        match series.iter_mut().find(|series| series.id == *id) {
+            Some(series) => {
+                if let Some(set) = fix.set {
+                    series.set = set;
+                    series.product.set = Some(set);
+                }
+                if let Some(name) = &fix.name {
+                    series.product.name = name.clone();
+                }
+                if let Some(kind) = fix.kind {
+                    series.kind = kind;
+                }
+            }
+            None => {
+                if let Some(set) = fix.set.filter(|_| !fix.skip) {
+                    println!("Adding series {id} from the overrides");
+                    series.push(Series {
+                        id: *id,
+                        set,
+                        product: Product {
+                            name: fix.name.clone().unwrap_or_default(),
+                            set: Some(set),
+                        },
+                        kind: fix.kind.unwrap_or(ProductKind::Other),
+                    });
+                }
+            }
+        }
+    }
+
+    series.retain(|series| !overrides.get(&series.id).is_some_and(|fix| fix.skip));
+    series
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dropdown() -> Vec<Series> {
+        vec![
+            Series::parse(
+                569107,
+                "BOOSTER PACK <br class=\"spInline\">-500 YEARS IN THE FUTURE- [OP-07]",
+            ),
+            Series::parse(569901, "PROMOTION CARD"),
+            Series::parse(569801, "Family Deck Set"),
+        ]
+    }
+
+    fn overrides(json: &str) -> Overrides {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn parses_dropdown_labels() {
+        let [booster, promo, other] = dropdown().try_into().unwrap();
+        assert_eq!(booster.set, SetId::Booster(7));
+        assert_eq!(booster.product.name, "500 YEARS IN THE FUTURE");
+        assert_eq!(booster.kind, ProductKind::BoosterPack);
+        assert_eq!(promo.set, SetId::Promo);
+        assert_eq!(promo.kind, ProductKind::Promotion);
+        assert_eq!(other.kind, ProductKind::Other);
+    }
+
+    #[test]
+    fn no_overrides_keep_the_dropdown() {
+        assert_eq!(apply_overrides(dropdown(), &Overrides::new()), dropdown());
+    }
+
+    #[test]
+    fn patches_series() {
+        let series = apply_overrides(
+            dropdown(),
+            &overrides(
+                r#"{ "569801": { "set": "ST21", "name": "Family Deck Set", "kind": "starter-deck" } }"#,
+            ),
+        );
+        let family = &series[2];
+        assert_eq!(family.id, 569801);
+        assert_eq!(family.set, SetId::Starter(21));
+        assert_eq!(family.product.set, Some(SetId::Starter(21)));
+        assert_eq!(family.product.name, "Family Deck Set");
+        assert_eq!(family.kind, ProductKind::StarterDeck);
+        assert_eq!(series[..2], dropdown()[..2]);
+    }
+
+    #[test]
+    fn skips_series() {
+        let series = apply_overrides(dropdown(), &overrides(r#"{ "569901": { "skip": true } }"#));
+        let ids: Vec<_> = series.iter().map(|series| series.id).collect();
+        assert_eq!(ids, [569107, 569801]);
+    }
+
+    #[test]
+    fn adds_series_missing_from_the_dropdown() {
+        let series = apply_overrides(
+            dropdown(),
+            &overrides(
+                r#"{
+                    "569999": { "set": "P", "name": "Tournament Pack", "kind": "promotion" },
+                    "569998": { "name": "No set given" },
+                    "569997": { "set": "P", "skip": true }
+                }"#,
+            ),
+        );
+        assert_eq!(series.len(), 4);
+        assert_eq!(
+            series[3],
+            Series {
+                id: 569999,
+                set: SetId::Promo,
+                product: Product {
+                    name: "Tournament Pack".to_string(),
+                    set: Some(SetId::Promo),
+                },
+                kind: ProductKind::Promotion,
+            }
+        );
+    }
+
+    #[test]
+    fn committed_overrides_are_valid() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("series_overrides.json");
+        assert!(path.exists());
+        load_overrides(&path).unwrap();
+    }
+}